@@ -0,0 +1,57 @@
+"""
+Time queries of the state of an expression, which are expected not to copy the
+comments attached to the state.
+
+Run with `python benches/state_queries.py` once the module is installed, for
+example with `maturin develop --release`. The script exits with an error if
+state-only queries of distinct instants get slower than queries that also
+read the comments.
+"""
+
+import sys
+import timeit
+from datetime import datetime, timedelta
+
+from opening_hours import OpeningHours
+
+EXPRESSION = (
+    'Mo-Fr 08:00-12:00,14:00-18:00 "office"; Sa 10:00-14:00 unknown "maybe"; '
+    "Dec 24-26 off; 2020-2030 Jan 01 off"
+)
+
+NUMBER = 10_000
+
+# Maximal ratio between the duration of state-only queries and the duration
+# of queries that also read the comments, over distinct instants.
+MAX_RATIO = 1.0
+
+
+def main():
+    oh = OpeningHours(EXPRESSION)
+    times = [datetime(2020, 11, 2) + timedelta(minutes=7 * i) for i in range(NUMBER)]
+
+    def state_only():
+        for time in times:
+            oh.state(time)
+            oh.is_open(time + timedelta(seconds=1))
+
+    def with_comments():
+        for time in times:
+            oh.comments_active_at(time)
+            oh.comments_active_at(time + timedelta(seconds=1))
+
+    results = {}
+
+    for name, bench in [("state only", state_only), ("with comments", with_comments)]:
+        results[name] = min(timeit.repeat(bench, number=1, repeat=5))
+        print(f"{name:>20}: {results[name] / NUMBER * 1e6:.2f} µs per pair of queries")
+
+    ratio = results["state only"] / results["with comments"]
+    print(f"{'ratio':>20}: {ratio:.2f} (max {MAX_RATIO:.2f})")
+
+    if ratio > MAX_RATIO:
+        sys.exit("state-only queries are slower than queries reading comments")
+
+
+if __name__ == "__main__":
+    main()
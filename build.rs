@@ -0,0 +1,35 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Name of the core crate in the lockfile.
+const CORE_PACKAGE: &str = "opening-hours";
+
+/// Read the version of the core crate that was resolved in `Cargo.lock`.
+fn core_version(lockfile: &str) -> Option<&str> {
+    let mut lines = lockfile.lines();
+
+    while let Some(line) = lines.next() {
+        if line.trim() == format!("name = \"{}\"", CORE_PACKAGE) {
+            return lines
+                .next()?
+                .trim()
+                .strip_prefix("version = \"")?
+                .strip_suffix('"');
+        }
+    }
+
+    None
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let lockfile_path = Path::new(&manifest_dir).join("Cargo.lock");
+    let lockfile = fs::read_to_string(&lockfile_path).unwrap_or_default();
+
+    println!("cargo:rerun-if-changed={}", lockfile_path.display());
+    println!(
+        "cargo:rustc-env=OPENING_HOURS_CORE_VERSION={}",
+        core_version(&lockfile).unwrap_or("unknown")
+    );
+}
@@ -0,0 +1,268 @@
+use std::cmp::{max, min};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use chrono::{Datelike, Duration, NaiveDateTime};
+
+use opening_hours::time_domain::{DateTimeRange, RuleKind, TimeDomain};
+
+use crate::selectors;
+use crate::types::NaiveDateTimeWrapper;
+
+/// Move a time by a duration, times that can't be moved are kept unchanged:
+/// this is the case of the end of ranges that never stop, which are past the
+/// dates supported by Python.
+fn move_time(time: NaiveDateTime, delta: Duration) -> NaiveDateTime {
+    if time >= NaiveDateTimeWrapper::max_py_value().into() {
+        return time;
+    }
+
+    time.checked_add_signed(delta).unwrap_or(time)
+}
+
+/// Merge consecutive ranges that have the same state and comments.
+fn merge_ranges<'c>(
+    ranges: impl Iterator<Item = DateTimeRange<'c>>,
+) -> impl Iterator<Item = DateTimeRange<'c>> {
+    let mut ranges = ranges.peekable();
+
+    std::iter::from_fn(move || {
+        let mut current = ranges.next()?;
+
+        while let Some(next) = ranges.peek() {
+            if next.range.start != current.range.end
+                || next.kind != current.kind
+                || next.comments != current.comments
+            {
+                break;
+            }
+
+            current.range.end = next.range.end;
+            ranges.next();
+        }
+
+        Some(current)
+    })
+}
+
+/// Closed range without comments.
+fn closed_range<'c>(range: Range<NaiveDateTime>) -> DateTimeRange<'c> {
+    DateTimeRange {
+        range,
+        kind: RuleKind::Closed,
+        comments: Vec::new(),
+    }
+}
+
+/// Time domain of an expression which can be shifted in time and restricted
+/// to a window.
+///
+/// The state of the domain at a given time is the state of the expression
+/// `shift` earlier, the dates of ranges and changes are moved accordingly.
+/// Outside of the window, if there is one, the domain is closed.
+#[derive(Clone, PartialEq)]
+pub struct Domain {
+    td: TimeDomain,
+    shift: Duration,
+    window: Option<Range<NaiveDateTime>>,
+}
+
+impl Hash for Domain {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The debug representation of rules is a function of their
+        // structure, which is what equality compares.
+        format!("{:?}", self.td.rules).hash(state);
+        self.shift.hash(state);
+        self.window.hash(state);
+    }
+}
+
+impl Domain {
+    pub fn new(td: TimeDomain) -> Self {
+        Self {
+            td,
+            shift: Duration::zero(),
+            window: None,
+        }
+    }
+
+    /// Rules of the expression, which don't depend on the shift.
+    pub fn time_domain(&self) -> &TimeDomain {
+        &self.td
+    }
+
+    pub fn shift(&self) -> Duration {
+        self.shift
+    }
+
+    /// Window out of which the domain is closed, it is given in times of the
+    /// domain and thus moves with the shift.
+    pub fn window(&self) -> Option<&Range<NaiveDateTime>> {
+        self.window.as_ref()
+    }
+
+    /// Get the same domain shifted by `delta` later in time, or `None` if the
+    /// total shift overflows.
+    pub fn shifted(&self, delta: Duration) -> Option<Self> {
+        let window = self
+            .window
+            .as_ref()
+            .map(|window| move_time(window.start, delta)..move_time(window.end, delta));
+
+        Some(Self {
+            td: self.td.clone(),
+            shift: self.shift.checked_add(&delta)?,
+            window,
+        })
+    }
+
+    /// Get the same domain, closed out of `window`. If the domain is already
+    /// restricted, the result is restricted to the intersection of both
+    /// windows.
+    pub fn restricted(&self, window: Range<NaiveDateTime>) -> Self {
+        let window = match &self.window {
+            Some(current) => max(current.start, window.start)..min(current.end, window.end),
+            None => window,
+        };
+
+        Self {
+            td: self.td.clone(),
+            shift: self.shift,
+            window: Some(window.start..max(window.start, window.end)),
+        }
+    }
+
+    pub fn in_window(&self, time: NaiveDateTime) -> bool {
+        self.window
+            .as_ref()
+            .map_or(true, |window| window.contains(&time))
+    }
+
+    /// Convert a time of the domain into the time evaluated by the
+    /// expression.
+    pub fn expression_time(&self, time: NaiveDateTime) -> NaiveDateTime {
+        move_time(time, -self.shift)
+    }
+
+    /// Convert a time evaluated by the expression into a time of the domain.
+    fn domain_time(&self, time: NaiveDateTime) -> NaiveDateTime {
+        move_time(time, self.shift)
+    }
+
+    fn domain_range<'c>(&self, mut dt_range: DateTimeRange<'c>) -> DateTimeRange<'c> {
+        dt_range.range =
+            self.domain_time(dt_range.range.start)..self.domain_time(dt_range.range.end);
+        dt_range
+    }
+
+    pub fn state(&self, time: NaiveDateTime) -> RuleKind {
+        if !self.in_window(time) {
+            return RuleKind::Closed;
+        }
+
+        self.td.state(self.expression_time(time))
+    }
+
+    pub fn is_open(&self, time: NaiveDateTime) -> bool {
+        self.in_window(time) && self.td.is_open(self.expression_time(time))
+    }
+
+    pub fn is_closed(&self, time: NaiveDateTime) -> bool {
+        !self.in_window(time) || self.td.is_closed(self.expression_time(time))
+    }
+
+    pub fn is_unknown(&self, time: NaiveDateTime) -> bool {
+        self.in_window(time) && self.td.is_unknown(self.expression_time(time))
+    }
+
+    /// Check if the domain may be open after `time`. This is a conservative
+    /// check that only relies on the kind and the years of rules.
+    pub fn may_open_after(&self, time: NaiveDateTime) -> bool {
+        if let Some(window) = &self.window {
+            if time >= window.end {
+                return false;
+            }
+        }
+
+        selectors::may_open_after(&self.td, self.expression_time(time).year())
+    }
+
+    pub fn next_change(&self, time: NaiveDateTime) -> NaiveDateTime {
+        if self.window.is_none() {
+            return self.domain_time(self.td.next_change(self.expression_time(time)));
+        }
+
+        self.iter_from(time)
+            .next()
+            .map(|dt_range| dt_range.range.end)
+            .unwrap_or_else(|| NaiveDateTimeWrapper::max_py_value().into())
+    }
+
+    pub fn iter_from(
+        &self,
+        from: NaiveDateTime,
+    ) -> Box<dyn Iterator<Item = DateTimeRange<'_>> + '_> {
+        let window = match &self.window {
+            Some(window) => window,
+            None => {
+                return Box::new(
+                    self.td
+                        .iter_from(self.expression_time(from))
+                        .map(move |dt_range| self.domain_range(dt_range)),
+                )
+            }
+        };
+
+        // The domain is closed before and after the window, inside of it
+        // ranges of the expression are truncated to the window. Ranges that
+        // meet at the edges of the window are merged if they don't differ.
+        let inner_start = max(from, window.start);
+        let window_end = window.end;
+        let before = closed_range(from..window.start);
+        let after =
+            closed_range(max(from, window_end)..NaiveDateTimeWrapper::max_py_value().into());
+
+        let inside = self
+            .td
+            .iter_from(self.expression_time(inner_start))
+            .map(move |dt_range| self.domain_range(dt_range))
+            .take_while(move |dt_range| {
+                inner_start < window_end && dt_range.range.start < window_end
+            })
+            .map(move |mut dt_range| {
+                dt_range.range.start = max(dt_range.range.start, inner_start);
+                dt_range.range.end = min(dt_range.range.end, window_end);
+                dt_range
+            });
+
+        let ranges = std::iter::once(before)
+            .chain(inside)
+            .chain(std::iter::once(after))
+            .filter(|dt_range| dt_range.range.start < dt_range.range.end);
+
+        Box::new(merge_ranges(ranges))
+    }
+
+    pub fn iter_range(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Box<dyn Iterator<Item = DateTimeRange<'_>> + '_> {
+        if self.window.is_none() {
+            return Box::new(
+                self.td
+                    .iter_range(self.expression_time(from), self.expression_time(to))
+                    .map(move |dt_range| self.domain_range(dt_range)),
+            );
+        }
+
+        Box::new(
+            self.iter_from(from)
+                .take_while(move |dt_range| dt_range.range.start < to)
+                .map(move |mut dt_range| {
+                    dt_range.range.end = min(dt_range.range.end, to);
+                    dt_range
+                }),
+        )
+    }
+}
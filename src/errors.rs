@@ -1,6 +1,6 @@
 use std::fmt;
 
-use pyo3::exceptions::PySyntaxError;
+use pyo3::exceptions::{PySyntaxError, PyValueError};
 use pyo3::prelude::*;
 
 #[derive(Debug)]
@@ -25,3 +25,121 @@ impl From<ParserError> for PyErr {
         PySyntaxError::new_err(parser_error.to_string())
     }
 }
+
+#[derive(Debug)]
+pub struct StrictModeError(Vec<&'static str>);
+
+impl std::error::Error for StrictModeError {}
+
+impl fmt::Display for StrictModeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expression is not canonical:\n{}", self.0.join("\n"))
+    }
+}
+
+impl From<Vec<&'static str>> for StrictModeError {
+    fn from(warnings: Vec<&'static str>) -> Self {
+        Self(warnings)
+    }
+}
+
+impl From<StrictModeError> for PyErr {
+    fn from(strict_error: StrictModeError) -> Self {
+        PySyntaxError::new_err(strict_error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingHolidaysError;
+
+impl std::error::Error for MissingHolidaysError {}
+
+impl fmt::Display for MissingHolidaysError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expression depends on holidays (PH/SH) but no holiday calendar was provided"
+        )
+    }
+}
+
+impl From<MissingHolidaysError> for PyErr {
+    fn from(error: MissingHolidaysError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct InputTooLongError {
+    pub length: usize,
+    pub max_length: usize,
+}
+
+impl std::error::Error for InputTooLongError {}
+
+impl fmt::Display for InputTooLongError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expression is {} bytes long, which exceeds the limit of {} bytes",
+            self.length, self.max_length
+        )
+    }
+}
+
+impl From<InputTooLongError> for PyErr {
+    fn from(error: InputTooLongError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct DateTimeParseError(pub String);
+
+impl std::error::Error for DateTimeParseError {}
+
+impl fmt::Display for DateTimeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not parse datetime: {:?}", self.0)
+    }
+}
+
+impl From<DateTimeParseError> for PyErr {
+    fn from(error: DateTimeParseError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct ExpressionDecodeError(pub std::str::Utf8Error);
+
+impl std::error::Error for ExpressionDecodeError {}
+
+impl fmt::Display for ExpressionDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expression is not valid UTF-8: {}", self.0)
+    }
+}
+
+impl From<ExpressionDecodeError> for PyErr {
+    fn from(error: ExpressionDecodeError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct NoNextChangeError;
+
+impl std::error::Error for NoNextChangeError {}
+
+impl fmt::Display for NoNextChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the state never changes after given time")
+    }
+}
+
+impl From<NoNextChangeError> for PyErr {
+    fn from(error: NoNextChangeError) -> Self {
+        PyValueError::new_err(error.to_string())
+    }
+}
@@ -0,0 +1,134 @@
+/// Selectors after which a bare range of hours such as `9-17` is considered to
+/// be a time span.
+const DAY_SELECTORS: &[&str] = &["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su", "PH", "SH"];
+
+/// Description of the corrections that can be applied by `fix`.
+pub const CORRECTIONS: &[&str] = &[REMOVED_WHITESPACE, COMPLETED_TIMES];
+
+const REMOVED_WHITESPACE: &str = "removed extra whitespace";
+const COMPLETED_TIMES: &str = "completed times written without leading zeros or minutes";
+
+/// Apply a small set of safe corrections to common mistakes found in opening
+/// hours expressions, the corrected expression is returned along with a
+/// description of the corrections that were applied.
+///
+/// Comments are never modified.
+pub fn fix(oh: &str) -> (String, Vec<&'static str>) {
+    let mut corrections = Vec::new();
+    let mut fixed_whitespace = oh.trim() != oh;
+    let mut fixed_times = false;
+
+    let segments: Vec<_> = oh
+        .trim()
+        .split('"')
+        .enumerate()
+        .map(|(i, segment)| {
+            // Odd segments are the content of comments.
+            if i % 2 == 1 {
+                return segment.to_string();
+            }
+
+            let collapsed = collapse_whitespace(segment);
+            fixed_whitespace |= collapsed != segment;
+
+            let mut prev_word: Option<&str> = None;
+
+            let words: Vec<_> = collapsed
+                .split(' ')
+                .map(|word| {
+                    let after_day = prev_word.map_or(false, |prev| {
+                        DAY_SELECTORS.iter().any(|day| prev.ends_with(day))
+                    });
+
+                    prev_word = Some(word);
+
+                    // Rule separators may stick to the last span of a rule.
+                    let (spans, separator) = match word.strip_suffix(';') {
+                        Some(spans) => (spans, ";"),
+                        None => (word, ""),
+                    };
+
+                    match fix_spans(spans, after_day) {
+                        Some(fixed) if fixed != spans => {
+                            fixed_times = true;
+                            fixed + separator
+                        }
+                        _ => word.to_string(),
+                    }
+                })
+                .collect();
+
+            words.join(" ")
+        })
+        .collect();
+
+    if fixed_whitespace {
+        corrections.push(REMOVED_WHITESPACE);
+    }
+
+    if fixed_times {
+        corrections.push(COMPLETED_TIMES);
+    }
+
+    (segments.join("\""), corrections)
+}
+
+/// Replace sequences of whitespaces with a single space.
+fn collapse_whitespace(segment: &str) -> String {
+    let mut result = String::with_capacity(segment.len());
+    let mut prev_is_space = false;
+
+    for c in segment.chars() {
+        if c.is_whitespace() {
+            if !prev_is_space {
+                result.push(' ');
+            }
+
+            prev_is_space = true;
+        } else {
+            result.push(c);
+            prev_is_space = false;
+        }
+    }
+
+    result
+}
+
+/// Rewrite a comma-separated list of time spans with times as `HH:MM`, spans
+/// made of bare hours are only accepted if `bare_hours` is set.
+fn fix_spans(word: &str, bare_hours: bool) -> Option<String> {
+    let spans: Option<Vec<_>> = word
+        .split(',')
+        .map(|span| {
+            let mut bounds = span.split('-');
+            let start = bounds.next()?;
+            let end = bounds.next()?;
+
+            if bounds.next().is_some()
+                || !(bare_hours || (start.contains(':') && end.contains(':')))
+            {
+                return None;
+            }
+
+            Some(format!("{}-{}", fix_time(start)?, fix_time(end)?))
+        })
+        .collect();
+
+    Some(spans?.join(","))
+}
+
+/// Rewrite a time written as `H`, `HH`, `H:MM` or `HH:MM` as `HH:MM`.
+fn fix_time(time: &str) -> Option<String> {
+    let (hours, minutes) = match time.find(':') {
+        Some(sep) => (&time[..sep], &time[sep + 1..]),
+        None => (time, "00"),
+    };
+
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+    if !is_number(hours) || hours.len() > 2 || !is_number(minutes) || minutes.len() != 2 {
+        return None;
+    }
+
+    Some(format!("{:0>2}:{}", hours, minutes))
+}
@@ -1,26 +1,343 @@
+mod domain;
 mod errors;
+mod fixes;
+mod lint;
+mod selectors;
+mod stubs;
 mod types;
 
+use std::cell::RefCell;
+use std::cmp::{max, min};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::sync::Arc;
 
-use chrono::offset::Local;
-use chrono::NaiveDateTime;
+use chrono::offset::{Local, Utc};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 
+use pyo3::class::basic::CompareOp;
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyDateTime, PyDict, PyList, PySlice, PyType};
 use pyo3::wrap_pyfunction;
+use pyo3::{PyIterProtocol, PyMappingProtocol, PyObjectProtocol};
 
+use opening_hours::time_domain::{DateTimeRange, RuleKind};
 use opening_hours::{parser, time_domain};
-use types::RangeIterator;
+use types::{
+    state_changes, BatchIterator, BoundaryIterator, ChangeIterator, EventIterator, Interval,
+    RangeIterator, StateTracker, Summary, Transition, WeekIterator,
+};
 
-use crate::errors::ParserError;
-use crate::types::{NaiveDateTimeWrapper, State};
+use crate::domain::Domain;
+use crate::errors::{
+    DateTimeParseError, InputTooLongError, MissingHolidaysError, NoNextChangeError, ParserError,
+    StrictModeError,
+};
+use crate::types::{
+    copy_comments, interval_tuple, split_daily_window, DurationWrapper, Expression, IntervalTuple,
+    NaiveDateTimeWrapper, NaiveDateWrapper, NaiveTimeWrapper, StartBound, State,
+};
 
-fn get_time(datetime: Option<NaiveDateTime>) -> NaiveDateTime {
-    datetime.unwrap_or_else(|| Local::now().naive_local())
+/// Clock used to get current time when it is omitted from a query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Clock {
+    Local,
+    Utc,
+}
+
+impl Clock {
+    fn parse(clock: &str) -> PyResult<Self> {
+        match clock {
+            "local" => Ok(Self::Local),
+            "utc" => Ok(Self::Utc),
+            _ => Err(PyValueError::new_err(
+                "clock must be either \"local\" or \"utc\"",
+            )),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Utc => "utc",
+        }
+    }
+
+    fn now(self) -> NaiveDateTime {
+        match self {
+            Self::Local => Local::now().naive_local(),
+            Self::Utc => Utc::now().naive_utc(),
+        }
+    }
+}
+
+fn get_time(datetime: Option<NaiveDateTime>, clock: Clock) -> NaiveDateTime {
+    datetime.unwrap_or_else(|| clock.now())
+}
+
+/// Add a duration to a datetime, the result is clamped to the range of
+/// datetimes supported by Python instead of overflowing.
+fn add_clamped(time: NaiveDateTime, duration: Duration) -> NaiveDateTime {
+    let min_time: NaiveDateTime = NaiveDateTimeWrapper::min_py_value().into();
+    let max_time: NaiveDateTime = NaiveDateTimeWrapper::max_py_value().into();
+
+    match time.checked_add_signed(duration) {
+        Some(result) => result.clamp(min_time, max_time),
+        None if duration < Duration::zero() => min_time,
+        None => max_time,
+    }
+}
+
+/// Get the first day of the week used to evaluate queries that only specify a
+/// weekday, which is the Monday of current week.
+fn reference_week(clock: Clock) -> NaiveDate {
+    let today = clock.now().date();
+    today - Duration::days(today.weekday().num_days_from_monday().into())
+}
+
+/// Get the range of time covered by a day.
+fn day_range(date: NaiveDate) -> Range<NaiveDateTime> {
+    date.and_hms(0, 0, 0)..(date + Duration::days(1)).and_hms(0, 0, 0)
+}
+
+/// Split a range of time into the subranges covering each day it overlaps.
+fn split_by_day(range: Range<NaiveDateTime>) -> impl Iterator<Item = Range<NaiveDateTime>> {
+    let mut start = range.start;
+
+    std::iter::from_fn(move || {
+        if start >= range.end {
+            return None;
+        }
+
+        let midnight = (start.date() + Duration::days(1)).and_hms(0, 0, 0);
+        let end = min(midnight, range.end);
+        let day_range = start..end;
+        start = end;
+        Some(day_range)
+    })
+}
+
+/// Format of the bounds of a window in the output of `to_ast_json`.
+const AST_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Read a window described by `to_ast_json`, which is missing or `None` if
+/// the opening hours are not restricted.
+fn window_from_py(window: Option<&PyAny>) -> PyResult<Option<Range<NaiveDateTime>>> {
+    let window = match window {
+        Some(window) if !window.is_none() => window,
+        _ => return Ok(None),
+    };
+
+    let bound = |key: &str| -> PyResult<NaiveDateTime> {
+        let bound: String = window.get_item(key)?.extract()?;
+
+        NaiveDateTime::parse_from_str(&bound, AST_TIME_FORMAT).map_err(|_| {
+            PyValueError::new_err(format!("invalid AST: invalid window bound `{}`", bound))
+        })
+    };
+
+    Ok(Some(bound("start")?..bound("end")?))
+}
+
+/// Describe the shift and the window of a domain into `ast`, as in the output
+/// of `to_ast_json`.
+fn domain_to_py(py: Python, domain: &Domain, ast: &PyDict) -> PyResult<()> {
+    ast.set_item("shift", domain.shift().num_minutes())?;
+
+    if let Some(window) = domain.window() {
+        let py_window = PyDict::new(py);
+        py_window.set_item("start", window.start.format(AST_TIME_FORMAT).to_string())?;
+        py_window.set_item("end", window.end.format(AST_TIME_FORMAT).to_string())?;
+        ast.set_item("window", py_window)?;
+    } else {
+        ast.set_item("window", py.None())?;
+    }
+
+    Ok(())
+}
+
+/// Apply the shift and the window described by `domain_to_py` to a domain,
+/// both are optional.
+fn domain_from_py(domain: Domain, ast: &PyDict) -> PyResult<Domain> {
+    let shift: i64 = match ast.get_item("shift") {
+        Some(shift) => shift.extract()?,
+        None => 0,
+    };
+
+    let domain = shift
+        .checked_mul(60_000)
+        .and_then(|shift| domain.shifted(Duration::milliseconds(shift)))
+        .ok_or_else(|| PyValueError::new_err("shift is out of range"))?;
+
+    Ok(match window_from_py(ast.get_item("window"))? {
+        Some(window) => domain.restricted(window),
+        None => domain,
+    })
+}
+
+/// Describe the rules of a time domain as in the output of `to_ast_json`.
+fn rules_to_py<'py>(py: Python<'py>, td: &time_domain::TimeDomain) -> PyResult<&'py PyList> {
+    let rules = PyList::empty(py);
+
+    for rule in &td.rules {
+        let operator = format!("{:?}", rule.operator).to_lowercase();
+        let comments: Vec<String> = rule.comments.iter().cloned().collect();
+
+        let py_rule = PyDict::new(py);
+        py_rule.set_item("kind", State::from(rule.kind).into_py(py))?;
+        py_rule.set_item("operator", operator)?;
+        py_rule.set_item("comments", comments)?;
+        py_rule.set_item(
+            "day_selector",
+            selectors::day_selector_to_py(py, &rule.day_selector)?,
+        )?;
+        py_rule.set_item(
+            "time_selector",
+            selectors::time_selector_to_py(py, &rule.time_selector)?,
+        )?;
+        rules.append(py_rule)?;
+    }
+
+    Ok(rules)
+}
+
+/// Check that a daily window of clock times given by the user is not empty.
+fn daily_window(
+    window: Option<(NaiveTimeWrapper, NaiveTimeWrapper)>,
+) -> PyResult<Option<(NaiveTime, NaiveTime)>> {
+    let window = window.map(|(start, end)| (start.into(), end.into()));
+
+    if let Some((start, end)) = window {
+        if start >= end {
+            return Err(PyValueError::new_err(
+                "daily_window must start before it ends",
+            ));
+        }
+    }
+
+    Ok(window)
+}
+
+/// Words used by `OpeningHours.status_line` for a supported locale.
+struct StatusWording {
+    locale: &'static str,
+    open: &'static str,
+    closed: &'static str,
+    unknown: &'static str,
+    opens: &'static str,
+    closes: &'static str,
+    changes: &'static str,
+    weekdays: [&'static str; 7],
+}
+
+const STATUS_WORDINGS: &[StatusWording] = &[
+    StatusWording {
+        locale: "en",
+        open: "Open",
+        closed: "Closed",
+        unknown: "Unknown",
+        opens: "opens",
+        closes: "closes",
+        changes: "changes",
+        weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    },
+    StatusWording {
+        locale: "fr",
+        open: "Ouvert",
+        closed: "Fermé",
+        unknown: "Inconnu",
+        opens: "ouvre",
+        closes: "ferme",
+        changes: "change",
+        weekdays: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+    },
+];
+
+/// Default maximal length of an expression in bytes, inputs larger than this
+/// are rejected before being parsed.
+const DEFAULT_MAX_LENGTH: usize = 65_536;
+
+/// Maximal number of days to look back when searching for the previous change
+/// of state.
+const MAX_LOOKBACK_DAYS: i64 = 10 * 366;
+
+/// Parse an expression that is at most `max_length` bytes long, when `strict`
+/// is set, constructs that are tolerated by the parser but not canonical are
+/// rejected.
+fn parse(oh: &str, strict: bool, max_length: usize) -> PyResult<time_domain::TimeDomain> {
+    if oh.len() > max_length {
+        return Err(InputTooLongError {
+            length: oh.len(),
+            max_length,
+        }
+        .into());
+    }
+
+    let td = parser::parse(oh).map_err(ParserError::from)?;
+
+    if strict {
+        let warnings = lint::lint(oh);
+
+        if !warnings.is_empty() {
+            return Err(StrictModeError::from(warnings).into());
+        }
+    }
+
+    Ok(td)
+}
+
+/// Parse an expression like `parse` does, when `tolerant` is set common
+/// mistakes are corrected before parsing. The expression that was parsed and
+/// the list of corrections that were applied are returned along with the
+/// result.
+fn parse_tolerant(
+    oh: &str,
+    strict: bool,
+    max_length: usize,
+    tolerant: bool,
+) -> PyResult<(time_domain::TimeDomain, String, Vec<&'static str>)> {
+    if !tolerant {
+        return Ok((parse(oh, strict, max_length)?, oh.to_string(), Vec::new()));
+    }
+
+    if oh.len() > max_length {
+        return Err(InputTooLongError {
+            length: oh.len(),
+            max_length,
+        }
+        .into());
+    }
+
+    // Corrections may make the expression slightly longer, the limit only
+    // applies to the input.
+    let (fixed, corrections) = fixes::fix(oh);
+    Ok((parse(&fixed, strict, usize::MAX)?, fixed, corrections))
 }
 
 /// Validate that input string is a correct opening hours description.
 ///
+/// Parameters
+/// ----------
+/// oh : Union[str, bytes]
+///     Opening hours description, bytes are decoded as UTF-8.
+/// strict : bool
+///     Also reject constructs tolerated by the parser that are not part of
+///     the canonical syntax, such as extra whitespaces or empty rules.
+/// max_length : int
+///     Maximal length of the expression in bytes, longer inputs are
+///     considered invalid without being parsed.
+/// tolerant : bool
+///     Correct common mistakes before parsing, such as extra whitespaces or
+///     times written without leading zeros or minutes (`Mo-Fr 9-17`).
+///
+/// Raises
+/// ------
+/// ValueError
+///     The expression is given as bytes that are not valid UTF-8.
+///
 /// Examples
 /// --------
 /// >>> opening_hours.validate("24/7")
@@ -28,36 +345,549 @@ fn get_time(datetime: Option<NaiveDateTime>) -> NaiveDateTime {
 ///
 /// >>> opening_hours.validate("24/24")
 /// False
+///
+/// >>> opening_hours.validate("Mo-Fr  10:00-18:00", strict=True)
+/// False
+///
+/// >>> opening_hours.validate("Mo-Fr 9-17", tolerant=True)
+/// True
+///
+/// >>> opening_hours.validate(b"24/7")
+/// True
+#[pyfunction]
+#[text_signature = "(oh, /, strict=False, max_length=65536, tolerant=False)"]
+#[args(
+    strict = "false",
+    max_length = "DEFAULT_MAX_LENGTH",
+    tolerant = "false"
+)]
+fn validate(oh: Expression, strict: bool, max_length: usize, tolerant: bool) -> bool {
+    parse_tolerant(oh.as_str(), strict, max_length, tolerant).is_ok()
+}
+
+/// Validate an expression and get its normalized form, in which rules are
+/// written with canonical selectors and separators.
+///
+/// Only expressions made of year, weekday and time selectors can be
+/// normalized: other selectors would be lost when the expression is written
+/// back.
+///
+/// Raises
+/// ------
+/// SyntaxError
+///     Given string is not in valid opening hours format.
+/// ValueError
+///     The expression exceeds the default maximal length, it is given as
+///     bytes that are not valid UTF-8, or it can't be normalized.
+///
+/// Examples
+/// --------
+/// >>> opening_hours.validate_and_normalize("Mo-Fr  10:00-18:00")
+/// 'Mo-Fr 10:00-18:00'
 #[pyfunction]
 #[text_signature = "(oh, /)"]
-fn validate(oh: &str) -> bool {
-    parser::parse(oh).is_ok()
+fn validate_and_normalize(py: Python, oh: Expression) -> PyResult<String> {
+    let td = parse(oh.as_str(), false, DEFAULT_MAX_LENGTH)?;
+    let ast = PyDict::new(py);
+    ast.set_item("rules", rules_to_py(py, &td)?)?;
+
+    // The expression is written back from a partial description of the
+    // rules, it must describe the exact same rules.
+    selectors::expression_from_py(ast)
+        .ok()
+        .filter(|normalized| {
+            parse(normalized, false, DEFAULT_MAX_LENGTH)
+                .map(|reparsed| format!("{:?}", reparsed.rules) == format!("{:?}", td.rules))
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| {
+            PyValueError::new_err("the expression uses selectors that can't be normalized")
+        })
 }
 
-/// Parse input opening hours description.
+/// Validate that input string is a correct opening hours description that
+/// can be fully evaluated with the available context.
+///
+/// Expressions depending on solar events (`sunrise`, `sunset`, ...) require
+/// coordinates and expressions depending on holidays (`PH` or `SH`) require a
+/// holiday calendar.
+///
+/// Parameters
+/// ----------
+/// oh : Union[str, bytes]
+///     Opening hours description, bytes are decoded as UTF-8.
+/// has_coords : bool
+///     Coordinates are available to compute solar events.
+/// has_holidays : bool
+///     A holiday calendar is available.
+///
+/// Raises
+/// ------
+/// ValueError
+///     The expression is given as bytes that are not valid UTF-8.
+///
+/// Examples
+/// --------
+/// >>> opening_hours.validate_evaluable("Mo-Fr sunrise-sunset")
+/// False
+///
+/// >>> opening_hours.validate_evaluable("Mo-Fr sunrise-sunset", has_coords=True)
+/// True
+#[pyfunction]
+#[text_signature = "(oh, /, has_coords=False, has_holidays=False)"]
+#[args(has_coords = "false", has_holidays = "false")]
+fn validate_evaluable(oh: Expression, has_coords: bool, has_holidays: bool) -> bool {
+    match parse(oh.as_str(), false, DEFAULT_MAX_LENGTH) {
+        Ok(td) => {
+            (has_coords || !selectors::uses_solar_events(&td))
+                && (has_holidays || !selectors::uses_holidays(&td))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Parse a datetime written in one of the common formats: ISO 8601,
+/// `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`.
+///
+/// Timezone offsets are ignored and the local time they specify is kept.
+///
+/// Raises
+/// ------
+/// ValueError
+///     Given string is not in a supported format.
+///
+/// Examples
+/// --------
+/// >>> opening_hours.parse_datetime("2020-11-02 10:30")
+/// datetime.datetime(2020, 11, 2, 10, 30)
+#[pyfunction]
+#[text_signature = "(s, /)"]
+fn parse_datetime(s: &str) -> PyResult<NaiveDateTimeWrapper> {
+    NaiveDateTimeWrapper::parse(s).ok_or_else(|| DateTimeParseError(s.to_string()).into())
+}
+
+/// Parse input opening hours description and collect notes about constructs
+/// that are not canonical or can't be fully evaluated.
 ///
 /// Raises
 /// ------
 /// SyntaxError
 ///     Given string is not in valid opening hours format.
+/// ValueError
+///     The expression exceeds the default maximal length or it is given as
+///     bytes that are not valid UTF-8.
+///
+/// Examples
+/// --------
+/// >>> oh, notes = opening_hours.parse_verbose("Mo-Fr  10:00-18:00; PH off")
+/// >>> notes
+/// ['expression contains consecutive or non-space whitespace',
+///  'expression depends on holidays (PH/SH), which are never matched']
+#[pyfunction]
+#[text_signature = "(oh, /)"]
+fn parse_verbose(oh: Expression) -> PyResult<(OpeningHours, Vec<&'static str>)> {
+    let oh = oh.as_str();
+    let td = parse(oh, false, DEFAULT_MAX_LENGTH)?;
+    let mut notes = lint::lint(oh);
+
+    if selectors::uses_holidays(&td) {
+        notes.push("expression depends on holidays (PH/SH), which are never matched");
+    }
+
+    Ok((
+        OpeningHours::from_domain(Domain::new(td), oh.to_string()),
+        notes,
+    ))
+}
+
+/// Parse input opening hours description.
+///
+/// Parameters
+/// ----------
+/// oh : Union[str, bytes]
+///     Opening hours description, bytes are decoded as UTF-8.
+/// strict_holidays : bool
+///     Fail if the expression depends on public or school holidays (`PH` or
+///     `SH` selectors). As no holiday calendar can be provided yet, these
+///     selectors never match.
+/// strict : bool
+///     Also reject constructs tolerated by the parser that are not part of
+///     the canonical syntax, such as extra whitespaces or empty rules.
+/// max_length : int
+///     Maximal length of the expression in bytes, longer inputs are rejected
+///     without being parsed.
+/// tolerant : bool
+///     Correct common mistakes before parsing, such as extra whitespaces or
+///     times written without leading zeros or minutes (`Mo-Fr 9-17`). The
+///     corrections that were applied are listed in `corrections`.
+/// clock : str
+///     Clock used to get current time when a query omits it, either "local"
+///     for the local time of the system or "utc".
+///
+/// Raises
+/// ------
+/// SyntaxError
+///     Given string is not in valid opening hours format, or is not canonical
+///     while `strict` is set.
+/// ValueError
+///     The expression depends on holidays while `strict_holidays` is set,
+///     the expression is longer than `max_length`, it is given as bytes that
+///     are not valid UTF-8 or `clock` is not supported.
 ///
 /// Examples
 /// --------
 /// >>> oh = OpeningHours("24/7")
 /// >>> oh.is_open()
 /// True
+///
+/// Iterating over the object yields successive time intervals of consistent
+/// state from current time, see `OpeningHours.intervals`.
+///
+/// >>> for start, end, state, comments in OpeningHours("24/7"):
+/// ...     print(state)
+/// open
+///
+/// Slicing the object with two dates or datetimes gives the list of time
+/// intervals in this window, if the start is omitted or `None` current time
+/// is used. Note that this differs from `OpeningHours.intervals`, where an
+/// explicit `None` stands for `datetime.min`.
+///
+/// >>> OpeningHours("Mo 10:00-12:00")[datetime(2020, 11, 2):date(2020, 11, 3)]
+/// [(datetime.datetime(2020, 11, 2, 0, 0), datetime.datetime(2020, 11, 2, 10, 0), 'closed', []),
+///  (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0), 'open', []),
+///  (datetime.datetime(2020, 11, 2, 12, 0), datetime.datetime(2020, 11, 3, 0, 0), 'closed', [])]
+///
+/// As a slice can't be given options, its comments are given as written in
+/// the expression: use `OpeningHours.intervals` to normalize them.
+///
+/// Methods expecting a datetime also accept a date, which stands for
+/// midnight, a pair `(date, time)` or a string in one of the formats supported
+/// by `parse_datetime`.
+///
+/// >>> OpeningHours("Mo 10:00-12:00").is_open((date(2020, 11, 2), time(11, 0)))
+/// True
+///
+/// >>> OpeningHours("Mo-Fr 9-17", tolerant=True).corrections
+/// ['completed times written without leading zeros or minutes']
 #[pyclass]
-#[text_signature = "(oh, /)"]
+#[derive(Clone)]
+#[text_signature = "(oh, /, strict_holidays=False, strict=False, max_length=65536, tolerant=False, clock='local')"]
 struct OpeningHours {
-    inner: Arc<time_domain::TimeDomain>,
+    inner: Arc<Domain>,
+    last_evaluation: RefCell<Option<Evaluation>>,
+    /// Corrections applied to the expression before it was parsed.
+    #[pyo3(get)]
+    corrections: Vec<&'static str>,
+    /// Expression that was parsed, after corrections were applied.
+    source: String,
+    /// Clock used to get current time when it is omitted from a query.
+    clock: Clock,
+}
+
+/// State and comments of a time domain at a given time, comments are only
+/// copied once they are queried.
+#[derive(Clone)]
+struct Evaluation {
+    time: NaiveDateTime,
+    kind: RuleKind,
+    comments: Option<Vec<String>>,
+}
+
+impl OpeningHours {
+    fn from_domain(domain: Domain, source: String) -> Self {
+        Self {
+            inner: Arc::new(domain),
+            last_evaluation: RefCell::new(None),
+            corrections: Vec::new(),
+            source,
+            clock: Clock::Local,
+        }
+    }
+
+    /// Get opening hours built from the same expression over another domain.
+    fn with_domain(&self, domain: Domain) -> Self {
+        Self {
+            corrections: self.corrections.clone(),
+            clock: self.clock,
+            ..Self::from_domain(domain, self.source.clone())
+        }
+    }
+
+    /// Evaluate the state and comments at a given time and pass the result
+    /// to `f`. The last evaluation is cached as callers often query several
+    /// properties of the same instant.
+    fn evaluate<T>(&self, time: NaiveDateTime, f: impl FnOnce(RuleKind, &[String]) -> T) -> T {
+        let mut last_evaluation = self.last_evaluation.borrow_mut();
+
+        if let Some(Evaluation {
+            time: cached_time,
+            kind,
+            comments: Some(comments),
+        }) = &*last_evaluation
+        {
+            if *cached_time == time {
+                return f(*kind, comments);
+            }
+        }
+
+        let (kind, comments) = match self.inner.iter_from(time).next() {
+            Some(dt_range) => (dt_range.kind, copy_comments(dt_range.comments, false)),
+            None => (RuleKind::Closed, Vec::new()),
+        };
+
+        let result = f(kind, &comments);
+
+        *last_evaluation = Some(Evaluation {
+            time,
+            kind,
+            comments: Some(comments),
+        });
+
+        result
+    }
+
+    /// Evaluate the state at a given time, this reuses the last evaluation if
+    /// it was made at the same time. The core crate still computes comments
+    /// to get the state, but they are not copied into owned strings until
+    /// `evaluate` is called for the same time.
+    fn evaluate_kind(&self, time: NaiveDateTime) -> RuleKind {
+        let mut last_evaluation = self.last_evaluation.borrow_mut();
+
+        if let Some(evaluation) = &*last_evaluation {
+            if evaluation.time == time {
+                return evaluation.kind;
+            }
+        }
+
+        let kind = self.inner.state(time);
+
+        *last_evaluation = Some(Evaluation {
+            time,
+            kind,
+            comments: None,
+        });
+
+        kind
+    }
+
+    /// Iterate over ranges of consistent state between `start` and `end`,
+    /// ranges overlapping the edges of the window are truncated.
+    fn iter_window(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> impl Iterator<Item = DateTimeRange<'_>> + '_ {
+        self.inner
+            .iter_range(start, end)
+            .map(move |mut dt_range| {
+                dt_range.range.start = max(dt_range.range.start, start);
+                dt_range.range.end = min(dt_range.range.end, end);
+                dt_range
+            })
+            .filter(|dt_range| dt_range.range.start < dt_range.range.end)
+    }
+
+    /// Get the date of the next change between open and not open after
+    /// `time`, unknown state being considered as closed. The scan stops once
+    /// the domain can't be open anymore.
+    fn next_open_change(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut tracker = StateTracker::default().with_collapse_unknown(true);
+
+        for dt_range in self.inner.iter_from(time) {
+            let is_change = tracker.track(&dt_range.range, dt_range.kind);
+
+            if is_change && dt_range.range.start > time {
+                return Some(dt_range.range.start);
+            }
+
+            if tracker.is_final(|| self.inner.may_open_after(dt_range.range.end)) {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Get the date of the next change of state after `time`, boundaries
+    /// where only the comments change are skipped.
+    fn next_state_change(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        state_changes(self.inner.iter_from(time))
+            .nth(1)
+            .map(|dt_range| dt_range.range.start)
+    }
+
+    /// Get the open ranges between `start` and `end`, contiguous open ranges
+    /// with different comments are merged together.
+    fn merged_open_ranges(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Vec<Range<NaiveDateTime>> {
+        let mut result: Vec<Range<NaiveDateTime>> = Vec::new();
+
+        for dt_range in self.iter_window(start, end) {
+            if dt_range.kind != RuleKind::Open {
+                continue;
+            }
+
+            match result.last_mut() {
+                Some(last) if last.end == dt_range.range.start => last.end = dt_range.range.end,
+                _ => result.push(dt_range.range),
+            }
+        }
+
+        result
+    }
+
+    /// Get the open ranges of the week starting at `week_start` as tuples
+    /// `(weekday, start, end)`, ranges crossing midnight are split.
+    fn week_open_ranges(
+        &self,
+        week_start: NaiveDate,
+    ) -> Vec<(u32, NaiveTimeWrapper, NaiveTimeWrapper)> {
+        let start = week_start.and_hms(0, 0, 0);
+        let end = start + Duration::days(7);
+
+        self.iter_window(start, end)
+            .filter(|dt_range| dt_range.kind == RuleKind::Open)
+            .flat_map(|dt_range| split_by_day(dt_range.range))
+            .map(|day_range| {
+                let weekday = day_range.start.weekday().num_days_from_monday();
+
+                let end_time = {
+                    if day_range.end.date() > day_range.start.date() {
+                        NaiveTimeWrapper::end_of_day()
+                    } else {
+                        day_range.end.time().into()
+                    }
+                };
+
+                (weekday, day_range.start.time().into(), end_time)
+            })
+            .collect()
+    }
+
+    /// Get the ranges of consistent state between `start` and `end`,
+    /// contiguous ranges with the same state are merged together.
+    fn state_timeline(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Vec<(Range<NaiveDateTime>, RuleKind)> {
+        let mut tracker = StateTracker::default();
+        let mut result: Vec<(Range<NaiveDateTime>, RuleKind)> = Vec::new();
+
+        for dt_range in self.iter_window(start, end) {
+            if tracker.track(&dt_range.range, dt_range.kind) {
+                result.push((dt_range.range, dt_range.kind));
+            } else if let Some((last, _)) = result.last_mut() {
+                last.end = dt_range.range.end;
+            }
+        }
+
+        result
+    }
+
+    /// Get the start of the next period of state `kind` that starts after
+    /// `time` and before `horizon` days later, optionally only considering
+    /// periods starting on a given weekday.
+    fn next_period_start(
+        &self,
+        kind: RuleKind,
+        time: NaiveDateTime,
+        weekday: Option<u32>,
+        horizon: u32,
+    ) -> Option<NaiveDateTime> {
+        let end = add_clamped(time, Duration::days(horizon.into()));
+
+        self.state_timeline(time, end)
+            .into_iter()
+            .filter(|(range, range_kind)| *range_kind == kind && range.start > time)
+            .map(|(range, _)| range.start)
+            .find(|start| {
+                weekday.map_or(true, |weekday| {
+                    start.weekday().num_days_from_monday() == weekday
+                })
+            })
+    }
+
+    /// Get the total duration of ranges of state `kind` between `start` and
+    /// `end`, only counting the parts of ranges inside of `window` of each
+    /// day if it is specified.
+    fn duration_of(
+        &self,
+        kind: RuleKind,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        window: Option<(NaiveTime, NaiveTime)>,
+    ) -> Duration {
+        self.iter_window(start, end)
+            .filter(|dt_range| dt_range.kind == kind)
+            .fold(Duration::zero(), |total, dt_range| match window {
+                None => total + (dt_range.range.end - dt_range.range.start),
+                Some(window) => split_daily_window(dt_range.range, window)
+                    .fold(total, |total, part| total + (part.end - part.start)),
+            })
+    }
+
+    /// Get the date of the last change of state happening before or at
+    /// `time`, returns `None` if the state didn't change during the last
+    /// `MAX_LOOKBACK_DAYS` days. Boundaries where only the comments change
+    /// are skipped.
+    fn prev_change(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut lookback = Duration::days(1);
+
+        loop {
+            let window_start = add_clamped(time, -lookback);
+
+            let current = state_changes(
+                self.iter_window(window_start, add_clamped(time, Duration::nanoseconds(1))),
+            )
+            .last()?;
+
+            if current.range.start > window_start {
+                return Some(current.range.start);
+            }
+
+            if lookback >= Duration::days(MAX_LOOKBACK_DAYS) {
+                return None;
+            }
+
+            lookback = min(lookback * 2, Duration::days(MAX_LOOKBACK_DAYS));
+        }
+    }
 }
 
 #[pymethods]
 impl OpeningHours {
     #[new]
-    fn new(oh: &str) -> PyResult<Self> {
+    #[args(
+        strict_holidays = "false",
+        strict = "false",
+        max_length = "DEFAULT_MAX_LENGTH",
+        tolerant = "false",
+        clock = "\"local\""
+    )]
+    fn new(
+        oh: Expression,
+        strict_holidays: bool,
+        strict: bool,
+        max_length: usize,
+        tolerant: bool,
+        clock: &str,
+    ) -> PyResult<Self> {
+        let clock = Clock::parse(clock)?;
+        let (inner, source, corrections) =
+            parse_tolerant(oh.as_str(), strict, max_length, tolerant)?;
+
+        if strict_holidays && selectors::uses_holidays(&inner) {
+            return Err(MissingHolidaysError.into());
+        }
+
         Ok(Self {
-            inner: Arc::new(parser::parse(oh).map_err(ParserError::from)?),
+            corrections,
+            clock,
+            ..Self::from_domain(Domain::new(inner), source)
         })
     }
 
@@ -69,14 +899,38 @@ impl OpeningHours {
     /// time : Optional[datetime]
     ///     Base time for the evaluation, current time will be used if it is
     ///     not specified.
+    /// unknown_outside_years : bool
+    ///     If all the rules of the expression are restricted to some years,
+    ///     years before the first one or after the last one are reported as
+    ///     "unknown" instead of "closed". Years are the ones evaluated by the
+    ///     expression, before it is shifted, and the state out of the window
+    ///     of restricted opening hours stays "closed".
     ///
     /// Examples
     /// --------
     /// >>> OpeningHours("24/7 off").state()
     /// "closed"
-    #[text_signature = "(self, time=None, /)"]
-    fn state(&self, time: Option<NaiveDateTimeWrapper>) -> State {
-        self.inner.state(get_time(time.map(Into::into))).into()
+    ///
+    /// >>> OpeningHours("2020-2025 Mo-Fr 10:00-18:00").state(
+    /// ...     datetime(2030, 1, 1), unknown_outside_years=True
+    /// ... )
+    /// "unknown"
+    #[text_signature = "(self, time=None, /, unknown_outside_years=False)"]
+    #[args(time = "None", unknown_outside_years = "false")]
+    fn state(&self, time: Option<NaiveDateTimeWrapper>, unknown_outside_years: bool) -> State {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        if unknown_outside_years && self.inner.in_window(time) {
+            if let Some((first, last)) = selectors::year_span(self.inner.time_domain()) {
+                let year = self.inner.expression_time(time).year();
+
+                if year < first || year > last {
+                    return State::Unknown;
+                }
+            }
+        }
+
+        self.evaluate_kind(time).into()
     }
 
     /// Check if current state is open.
@@ -86,14 +940,58 @@ impl OpeningHours {
     /// time : Optional[datetime]
     ///     Base time for the evaluation, current time will be used if it is
     ///     not specified.
+    /// grace : Optional[timedelta]
+    ///     Also consider the state as open if an open range ended less than
+    ///     `grace` before `time` or starts less than `grace` after it. This
+    ///     applies whether the current state is closed or unknown.
+    /// min_remaining : Optional[timedelta]
+    ///     Only consider the state as open if it stays open for at least this
+    ///     duration after `time`, `grace` doesn't apply when this is set.
     ///
     /// Examples
     /// --------
     /// >>> OpeningHours("24/7").is_open()
     /// True
-    #[text_signature = "(self, time=None, /)"]
-    fn is_open(&self, time: Option<NaiveDateTimeWrapper>) -> bool {
-        self.inner.is_open(get_time(time.map(Into::into)))
+    ///
+    /// >>> OpeningHours("Mo 10:00-18:00").is_open(
+    /// ...     datetime(2020, 11, 2, 18, 10), grace=timedelta(minutes=15)
+    /// ... )
+    /// True
+    ///
+    /// >>> OpeningHours("Mo 10:00-18:00").is_open(
+    /// ...     datetime(2020, 11, 2, 17, 45), min_remaining=timedelta(minutes=30)
+    /// ... )
+    /// False
+    #[text_signature = "(self, time=None, /, grace=None, min_remaining=None)"]
+    fn is_open(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        grace: Option<DurationWrapper>,
+        min_remaining: Option<DurationWrapper>,
+    ) -> bool {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        if let Some(min_remaining) = min_remaining {
+            let min_remaining: Duration = min_remaining.into();
+
+            return self.evaluate_kind(time) == RuleKind::Open
+                && self
+                    .iter_window(time, add_clamped(time, min_remaining))
+                    .all(|dt_range| dt_range.kind == RuleKind::Open);
+        }
+
+        if self.evaluate_kind(time) == RuleKind::Open {
+            return true;
+        }
+
+        match grace {
+            Some(grace) => {
+                let grace: Duration = grace.into();
+                self.iter_window(add_clamped(time, -grace), add_clamped(time, grace))
+                    .any(|dt_range| dt_range.kind == RuleKind::Open)
+            }
+            None => false,
+        }
     }
 
     /// Check if current state is closed.
@@ -110,7 +1008,8 @@ impl OpeningHours {
     /// True
     #[text_signature = "(self, time=None, /)"]
     fn is_closed(&self, time: Option<NaiveDateTimeWrapper>) -> bool {
-        self.inner.is_closed(get_time(time.map(Into::into)))
+        let time = get_time(time.map(Into::into), self.clock);
+        self.evaluate_kind(time) == RuleKind::Closed
     }
 
     /// Check if current state is unknown.
@@ -127,7 +1026,8 @@ impl OpeningHours {
     /// True
     #[text_signature = "(self, time=None, /)"]
     fn is_unknown(&self, time: Option<NaiveDateTimeWrapper>) -> bool {
-        self.inner.is_unknown(get_time(time.map(Into::into)))
+        let time = get_time(time.map(Into::into), self.clock);
+        self.evaluate_kind(time) == RuleKind::Unknown
     }
 
     /// Get the date for next change of state.
@@ -137,51 +1037,2690 @@ impl OpeningHours {
     /// time : Optional[datetime]
     ///     Base time for the evaluation, current time will be used if it is
     ///     not specified.
+    /// no_change : str
+    ///     What to do if the state never changes: "none" returns `None`,
+    ///     "max" returns `datetime.max` and "raise" raises a `ValueError`.
+    /// collapse_unknown : bool
+    ///     Consider unknown state as closed, so that only changes between
+    ///     open and not open are reported.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     The state never changes and `no_change` is "raise", or `no_change`
+    ///     is not a supported value.
     ///
     /// Examples
     /// --------
     /// >>> OpeningHours("2099Mo-Su 12:30-17:00").next_change()
     /// datetime.datetime(2099, 1, 1, 12, 30)
-    #[text_signature = "(self, time=None, /)"]
-    fn next_change(&self, time: Option<NaiveDateTimeWrapper>) -> NaiveDateTimeWrapper {
-        self.inner
-            .next_change(get_time(time.map(Into::into)))
-            .into()
+    ///
+    /// >>> OpeningHours("24/7").next_change(no_change="max")
+    /// datetime.datetime(9999, 12, 31, 23, 59, 59, 999999)
+    #[text_signature = "(self, time=None, /, no_change='none', collapse_unknown=False)"]
+    #[args(time = "None", no_change = "\"none\"", collapse_unknown = "false")]
+    fn next_change(
+        &self,
+        py: Python,
+        time: Option<NaiveDateTimeWrapper>,
+        no_change: &str,
+        collapse_unknown: bool,
+    ) -> PyResult<PyObject> {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        let next: NaiveDateTimeWrapper = {
+            if collapse_unknown {
+                self.next_open_change(time)
+                    .map(Into::into)
+                    .unwrap_or_else(NaiveDateTimeWrapper::max_py_value)
+            } else {
+                self.inner.next_change(time).into()
+            }
+        };
+
+        if next < NaiveDateTimeWrapper::max_py_value() {
+            return Ok(next.into_py(py));
+        }
+
+        match no_change {
+            "none" => Ok(py.None()),
+            "max" => Ok(PyDateTime::new(py, 9999, 12, 31, 23, 59, 59, 999_999, None)?.into_py(py)),
+            "raise" => Err(NoNextChangeError.into()),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported value for no_change: {:?}",
+                no_change
+            ))),
+        }
+    }
+
+    /// Get details about the next change of state: the date of the change,
+    /// the new state and the comments attached to it.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// source_comments : bool
+    ///     Return the comments of the state that is ending instead of the
+    ///     comments of the state that is starting.
+    /// structured : bool
+    ///     Return a `Transition` object, which also holds the state that is
+    ///     ending, instead of a tuple.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Returns `None` if the state never changes.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours('Mo-Fr 10:00-18:00 "by appointment"')
+    /// >>> oh.next_change_detail(datetime(2020, 11, 2, 12))
+    /// (datetime.datetime(2020, 11, 2, 18, 0), 'closed', [])
+    /// >>> oh.next_change_detail(datetime(2020, 11, 2, 12), source_comments=True)
+    /// (datetime.datetime(2020, 11, 2, 18, 0), 'closed', ['by appointment'])
+    /// >>> oh.next_change_detail(datetime(2020, 11, 2, 12), structured=True)
+    /// Transition(at=2020-11-02 18:00:00, from_state="open", to_state="closed", comments=[])
+    #[text_signature = "(self, time=None, /, source_comments=False, structured=False, normalize_comments=False)"]
+    #[args(
+        time = "None",
+        source_comments = "false",
+        structured = "false",
+        normalize_comments = "false"
+    )]
+    fn next_change_detail(
+        &self,
+        py: Python,
+        time: Option<NaiveDateTimeWrapper>,
+        source_comments: bool,
+        structured: bool,
+        normalize_comments: bool,
+    ) -> Option<PyObject> {
+        let mut ranges = self
+            .inner
+            .iter_from(get_time(time.map(Into::into), self.clock));
+        let current = ranges.next()?;
+        let next = ranges.next()?;
+
+        let comments = {
+            if source_comments {
+                current.comments
+            } else {
+                next.comments
+            }
+        };
+
+        let transition = Transition {
+            at: next.range.start.into(),
+            from_state: current.kind.into(),
+            to_state: next.kind.into(),
+            comments: copy_comments(comments, normalize_comments),
+        };
+
+        Some({
+            if structured {
+                transition.into_py(py)
+            } else {
+                (transition.at, transition.to_state, transition.comments).into_py(py)
+            }
+        })
     }
 
     /// Give an iterator that yields successive time intervals of consistent
     /// state.
     ///
+    /// Closed and unknown intervals are yielded as well as open ones, so that
+    /// successive intervals tile the timeline: each interval starts where the
+    /// previous one ended. Only `comment_contains`, `inclusive_end` and
+    /// `daily_window` can leave gaps in the output.
+    ///
     /// Parameters
     /// ----------
     /// start: Optional[datetime]
     ///     Initial time for the iterator, current time will be used if it is
-    ///     not specified.
+    ///     not specified. A token given by `RangeIterator.resume_token` can
+    ///     also be used to resume a previous iteration. If `None` is given
+    ///     explicitly, the iterator starts at `datetime.min`: this covers
+    ///     thousands of years and `end` should be specified.
     /// end : Optional[datetime]
     ///     Maximal time for the iterator, the iterator will continue until
-    ///     year 9999 if it no max is specified.
+    ///     year 9999 if it no max is specified. This instant is excluded: an
+    ///     interval starting at `end` is never yielded.
+    /// translate : Optional[Callable[[str], str]]
+    ///     Function applied to comments before they are returned, it is only
+    ///     called once for each distinct comment.
+    /// structured : bool
+    ///     Yield `Interval` objects, with named attributes and a `duration`,
+    ///     instead of tuples `(start, end, state, comments)`.
+    /// comment_contains : Optional[str]
+    ///     Only yield intervals having a comment that contains this string,
+    ///     the filter applies to comments before they are translated.
+    /// clip_start_to_now : bool
+    ///     Never yield intervals before current time, even if `start` is in
+    ///     the past: the first interval is clipped to start now.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces, this is done
+    ///     before they are translated.
+    /// inclusive_end : bool
+    ///     Yield the last interval clipped to `end` if it extends past it,
+    ///     when disabled this interval is dropped and only intervals that are
+    ///     fully contained in the window are yielded.
+    /// daily_window : Optional[Tuple[time, time]]
+    ///     Only yield the parts of intervals between these clock times of
+    ///     each day, intervals are clipped to this window and the time
+    ///     outside of it is skipped.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If `daily_window` doesn't start before it ends.
     ///
     /// Examples
     /// --------
-    /// TODO
-    #[text_signature = "(self, start=None, end=None, /)"]
+    /// >>> oh = OpeningHours('Mo 10:00-12:00 "sur rendez-vous"')
+    /// >>> translations = {"sur rendez-vous": "by appointment"}
+    /// >>> next(oh.intervals(datetime(2020, 11, 2, 10), translate=translations.get))
+    /// (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0), 'open', ['by appointment'])
+    ///
+    /// >>> oh = OpeningHours("Mo 06:00-18:00")
+    /// >>> [
+    /// ...     interval[:3]
+    /// ...     for interval in oh.intervals(
+    /// ...         datetime(2020, 11, 2), datetime(2020, 11, 3),
+    /// ...         daily_window=(time(8), time(20)),
+    /// ...     )
+    /// ... ]
+    /// [(datetime.datetime(2020, 11, 2, 8, 0), datetime.datetime(2020, 11, 2, 18, 0), 'open'),
+    ///  (datetime.datetime(2020, 11, 2, 18, 0), datetime.datetime(2020, 11, 2, 20, 0), 'closed')]
+    #[text_signature = "(self, start=..., end=None, /, translate=None, structured=False, comment_contains=None, clip_start_to_now=False, normalize_comments=False, inclusive_end=True, daily_window=None)"]
+    #[args(
+        start = "StartBound::Now",
+        end = "None",
+        translate = "None",
+        structured = "false",
+        comment_contains = "None",
+        clip_start_to_now = "false",
+        normalize_comments = "false",
+        inclusive_end = "true",
+        daily_window = "None"
+    )]
+    // Each parameter is a keyword argument of the Python method: grouping
+    // them into a struct would only move the list into the `#[args]`.
+    #[allow(clippy::too_many_arguments)]
     fn intervals(
+        &self,
+        start: StartBound,
+        end: Option<NaiveDateTimeWrapper>,
+        translate: Option<PyObject>,
+        structured: bool,
+        comment_contains: Option<String>,
+        clip_start_to_now: bool,
+        normalize_comments: bool,
+        inclusive_end: bool,
+        daily_window: Option<(NaiveTimeWrapper, NaiveTimeWrapper)>,
+    ) -> PyResult<RangeIterator> {
+        let daily_window = self::daily_window(daily_window)?;
+        let mut start = start.resolve(get_time(None, self.clock));
+
+        if clip_start_to_now {
+            start = max(start, get_time(None, self.clock));
+        }
+
+        Ok(
+            RangeIterator::new(self.inner.clone(), start, end.map(Into::into))
+                .with_translate(translate)
+                .with_structured(structured)
+                .with_comment_contains(comment_contains)
+                .with_normalize_comments(normalize_comments)
+                .with_inclusive_end(inclusive_end)
+                .with_daily_window(daily_window),
+        )
+    }
+
+    /// Give an iterator that yields successive changes of state, each change
+    /// is given with the state before and after it.
+    ///
+    /// Only actual changes of state are reported: a boundary between two
+    /// ranges with the same state but different comments is skipped.
+    ///
+    /// Parameters
+    /// ----------
+    /// start: Optional[datetime]
+    ///     Initial time for the iterator, current time will be used if it is
+    ///     not specified or `None`. Note that this differs from `intervals`,
+    ///     where an explicit `None` stands for `datetime.min`.
+    /// end : Optional[datetime]
+    ///     Maximal time for the iterator, the iterator will continue until
+    ///     year 9999 if it no max is specified.
+    /// structured : bool
+    ///     Yield `Transition` objects, which also hold the comments attached
+    ///     to the new state, instead of tuples.
+    /// normalize_comments : bool
+    ///     Trim comments of `Transition` objects and collapse their inner
+    ///     whitespaces.
+    /// collapse_unknown : bool
+    ///     Consider unknown state as closed, so that only changes between
+    ///     open and not open are reported.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo-Fr 10:00-18:00; Sa 10:00-12:00 unknown")
+    /// >>> list(oh.changes(datetime(2020, 11, 6), datetime(2020, 11, 8)))
+    /// [(datetime.datetime(2020, 11, 6, 10, 0), 'closed', 'open'),
+    ///  (datetime.datetime(2020, 11, 6, 18, 0), 'open', 'closed'),
+    ///  (datetime.datetime(2020, 11, 7, 10, 0), 'closed', 'unknown'),
+    ///  (datetime.datetime(2020, 11, 7, 12, 0), 'unknown', 'closed')]
+    /// >>> list(oh.changes(datetime(2020, 11, 6), datetime(2020, 11, 8), collapse_unknown=True))
+    /// [(datetime.datetime(2020, 11, 6, 10, 0), 'closed', 'open'),
+    ///  (datetime.datetime(2020, 11, 6, 18, 0), 'open', 'closed')]
+    #[text_signature = "(self, start=None, end=None, /, structured=False, normalize_comments=False, collapse_unknown=False)"]
+    #[args(
+        start = "None",
+        end = "None",
+        structured = "false",
+        normalize_comments = "false",
+        collapse_unknown = "false"
+    )]
+    fn changes(
+        &self,
+        start: Option<NaiveDateTimeWrapper>,
+        end: Option<NaiveDateTimeWrapper>,
+        structured: bool,
+        normalize_comments: bool,
+        collapse_unknown: bool,
+    ) -> ChangeIterator {
+        ChangeIterator::new(RangeIterator::new(
+            self.inner.clone(),
+            get_time(start.map(Into::into), self.clock),
+            end.map(Into::into),
+        ))
+        .with_structured(structured)
+        .with_normalize_comments(normalize_comments)
+        .with_collapse_unknown(collapse_unknown)
+    }
+
+    /// Get the total number of open minutes for each day of the week over a
+    /// time window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Returns a list of 7 integers starting with Monday. Ranges overlapping
+    /// the edges of the window are only counted for their part inside it.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").open_minutes_by_weekday(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 16)
+    /// ... )
+    /// [240, 0, 0, 0, 0, 0, 0]
+    #[text_signature = "(self, start, end, /)"]
+    fn open_minutes_by_weekday(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> Vec<i64> {
+        let mut minutes = vec![0; 7];
+
+        for dt_range in self.iter_window(start.into(), end.into()) {
+            if dt_range.kind != RuleKind::Open {
+                continue;
+            }
+
+            for day_range in split_by_day(dt_range.range) {
+                let weekday = day_range.start.weekday().num_days_from_monday();
+                minutes[weekday as usize] += (day_range.end - day_range.start).num_minutes();
+            }
+        }
+
+        minutes
+    }
+
+    /// Count the number of changes of state in a time window.
+    ///
+    /// Boundaries where only the comments change are not counted.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").total_changes(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 9)
+    /// ... )
+    /// 10
+    #[text_signature = "(self, start, end, /)"]
+    fn total_changes(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> usize {
+        state_changes(self.iter_window(start.into(), end.into()))
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// Export the parsed structure of the expression as JSON.
+    ///
+    /// The output holds the list of rules, each rule being described by its
+    /// kind, operator, comments and selectors:
+    ///
+    /// - `day_selector` holds the ranges of years and weekdays, weekdays
+    ///   being either a range of days or a kind of holiday. Other day
+    ///   selectors, such as months or weeks, are only given in `raw` as a
+    ///   debug representation, which is not meant to be stable.
+    /// - `time_selector` holds the list of time spans, each bound of a span
+    ///   is either a time `HH:MM` or a solar event with an offset in minutes.
+    ///
+    /// The shift of the opening hours, see `shift`, is given in minutes next
+    /// to the rules. If the opening hours are restricted to a window, see
+    /// `restrict`, its bounds are also given, in ISO 8601 format.
+    ///
+    /// The output can be turned back into opening hours with `from_ast_json`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> print(OpeningHours('Mo-Fr 10:00-12:00 "lunch"').to_ast_json())
+    /// {"rules": [{"kind": "open", "operator": "normal", "comments": ["lunch"], "day_selector": {"year": [], "weekday": [{"start": "Mo", "end": "Fr", "nth": [true, true, true, true, true], "offset": 0}], "raw": "..."}, "time_selector": [{"start": "10:00", "end": "12:00"}]}], "shift": 0, "window": null}
+    #[text_signature = "(self, /)"]
+    fn to_ast_json(&self, py: Python) -> PyResult<String> {
+        let ast = PyDict::new(py);
+        ast.set_item("rules", rules_to_py(py, self.inner.time_domain())?)?;
+        domain_to_py(py, &self.inner, ast)?;
+
+        py.import("json")?
+            .getattr("dumps")?
+            .call1((ast,))?
+            .extract()
+    }
+
+    /// Build opening hours from the rules exported by `to_ast_json`.
+    ///
+    /// Rules are written back as an expression from their kind, operator,
+    /// comments, years, weekdays, holidays and time spans. When a rule gives
+    /// the `raw` representation of its day selectors, it must match the
+    /// rebuilt rule: this way a rule using other selectors, such as months or
+    /// weeks, is rejected instead of silently applying to more days. The shift
+    /// and the window are optional.
+    ///
+    /// Parameters
+    /// ----------
+    /// ast : str
+    ///     JSON document in the format of `to_ast_json`.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If the document doesn't describe rules that can be written as an
+    ///     expression.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours('Mo-Fr 10:00-12:00 "lunch"')
+    /// >>> OpeningHours.from_ast_json(oh.to_ast_json()).state(datetime(2020, 11, 2, 11))
+    /// "open"
+    #[classmethod]
+    #[text_signature = "(cls, ast, /)"]
+    fn from_ast_json(cls: &PyType, ast: &str) -> PyResult<Self> {
+        let ast = cls.py().import("json")?.getattr("loads")?.call1((ast,))?;
+        let expression = selectors::expression_from_py(ast)?;
+
+        let inner = parser::parse(&expression).map_err(|err| {
+            PyValueError::new_err(format!("invalid AST: {}", ParserError::from(err)))
+        })?;
+
+        selectors::check_raw_day_selectors(ast, &inner)?;
+
+        let inner = domain_from_py(Domain::new(inner), ast.downcast()?)?;
+        Ok(Self::from_domain(inner, expression))
+    }
+
+    /// Get the time ranges where two expressions disagree on the state.
+    ///
+    /// Parameters
+    /// ----------
+    /// other : OpeningHours
+    ///     Expression to compare with.
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Returns a list of `((start, end), self_state, other_state)`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00").diff(
+    /// ...     OpeningHours("Mo 10:00-12:00"), datetime(2020, 11, 2), datetime(2020, 11, 3)
+    /// ... )
+    /// [((datetime.datetime(2020, 11, 2, 12, 0), datetime.datetime(2020, 11, 2, 18, 0)), 'open', 'closed')]
+    #[text_signature = "(self, other, start, end, /)"]
+    fn diff(
+        &self,
+        other: PyRef<OpeningHours>,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> Vec<((NaiveDateTimeWrapper, NaiveDateTimeWrapper), State, State)> {
+        let start = start.into();
+        let end = end.into();
+        let mut self_ranges = self.iter_window(start, end).peekable();
+        let mut other_ranges = other.iter_window(start, end).peekable();
+        let mut result: Vec<(Range<NaiveDateTime>, RuleKind, RuleKind)> = Vec::new();
+        let mut cursor = start;
+
+        // Both iterators cover the whole window without gaps, so the window
+        // can be walked through by stopping at the boundaries of both.
+        while let (Some(self_range), Some(other_range)) = (self_ranges.peek(), other_ranges.peek())
+        {
+            let self_end = self_range.range.end;
+            let self_kind = self_range.kind;
+            let other_end = other_range.range.end;
+            let other_kind = other_range.kind;
+            let sub_end = min(self_end, other_end);
+
+            if self_kind != other_kind {
+                match result.last_mut() {
+                    Some((range, last_self_kind, last_other_kind))
+                        if range.end == cursor
+                            && *last_self_kind == self_kind
+                            && *last_other_kind == other_kind =>
+                    {
+                        range.end = sub_end
+                    }
+                    _ => result.push((cursor..sub_end, self_kind, other_kind)),
+                }
+            }
+
+            if self_end == sub_end {
+                self_ranges.next();
+            }
+
+            if other_end == sub_end {
+                other_ranges.next();
+            }
+
+            cursor = sub_end;
+        }
+
+        result
+            .into_iter()
+            .map(|(range, self_kind, other_kind)| {
+                (
+                    (range.start.into(), range.end.into()),
+                    self_kind.into(),
+                    other_kind.into(),
+                )
+            })
+            .collect()
+    }
+    /// Get the same opening hours shifted later in time by a duration.
+    ///
+    /// The state of the result at any time is the state of this expression
+    /// `delta` earlier, a negative duration shifts opening hours earlier.
+    /// Shifts of successive calls add up.
+    ///
+    /// Day selectors are shifted along with time spans: a span moved past
+    /// midnight applies to the next day, for example `Mo 23:00-24:00` shifted
+    /// by two hours is open on Tuesday from 01:00 to 02:00.
+    ///
+    /// Parameters
+    /// ----------
+    /// delta : timedelta
+    ///     Duration of the shift, which must be a whole number of minutes.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If the duration is not a whole number of minutes or if the total
+    ///     shift overflows.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo 10:00-12:00").shift(timedelta(hours=1))
+    /// >>> oh.next_change(datetime(2020, 11, 2))
+    /// datetime.datetime(2020, 11, 2, 11, 0)
+    /// >>> oh.state(datetime(2020, 11, 2, 12, 30))
+    /// "open"
+    #[text_signature = "(self, delta, /)"]
+    fn shift(&self, delta: DurationWrapper) -> PyResult<Self> {
+        let delta: Duration = delta.into();
+
+        if delta != Duration::minutes(delta.num_minutes()) {
+            return Err(PyValueError::new_err(
+                "shift must be a whole number of minutes",
+            ));
+        }
+
+        let inner = self
+            .inner
+            .shifted(delta)
+            .ok_or_else(|| PyValueError::new_err("shift is out of range"))?;
+
+        Ok(self.with_domain(inner))
+    }
+    /// Get the same opening hours restricted to a time window, out of which
+    /// the state is closed.
+    ///
+    /// Restrictions of successive calls intersect, and the window moves along
+    /// with the opening hours if they are shifted afterwards.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If the end of the window is before its start.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("24/7").restrict(datetime(2020, 11, 2), datetime(2020, 11, 3))
+    /// >>> oh.state(datetime(2020, 11, 1, 12))
+    /// "closed"
+    /// >>> oh.state(datetime(2020, 11, 2, 12))
+    /// "open"
+    /// >>> oh.next_change(datetime(2020, 11, 2, 12))
+    /// datetime.datetime(2020, 11, 3, 0, 0)
+    #[text_signature = "(self, start, end, /)"]
+    fn restrict(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> PyResult<Self> {
+        let start: NaiveDateTime = start.into();
+        let end: NaiveDateTime = end.into();
+
+        if end < start {
+            return Err(PyValueError::new_err(
+                "end of the window is before its start",
+            ));
+        }
+
+        Ok(self.with_domain(self.inner.restricted(start..end)))
+    }
+
+    /// Get, for each state, the number of ranges and their total duration in
+    /// a time window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Ranges overlapping the edges of the window are only counted for their
+    /// part inside it.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").state_breakdown(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3)
+    /// ... )
+    /// {'open': (1, datetime.timedelta(seconds=28800)),
+    ///  'closed': (2, datetime.timedelta(seconds=57600)),
+    ///  'unknown': (0, datetime.timedelta(0))}
+    #[text_signature = "(self, start, end, /)"]
+    fn state_breakdown(
+        &self,
+        py: Python,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> PyResult<PyObject> {
+        // States are listed in a fixed order, which is kept by the dict.
+        const STATES: [State; 3] = [State::Open, State::Closed, State::Unknown];
+        let mut breakdown = [(0, Duration::zero()); 3];
+
+        for dt_range in self.iter_window(start.into(), end.into()) {
+            let state = State::from(dt_range.kind);
+            let index = STATES
+                .iter()
+                .position(|&s| s == state)
+                .expect("missing state");
+            let (count, duration) = &mut breakdown[index];
+            *count += 1;
+            *duration += dt_range.range.end - dt_range.range.start;
+        }
+
+        let py_breakdown = PyDict::new(py);
+
+        for (state, (count, duration)) in STATES.iter().zip(breakdown.iter()) {
+            let value: PyObject = (*count, DurationWrapper::from(*duration)).into_py(py);
+            py_breakdown.set_item(state.as_str(), value)?;
+        }
+
+        Ok(py_breakdown.into())
+    }
+
+    /// Check if the state is open for a weekday and a time of the day.
+    ///
+    /// The evaluation is performed on current week, thus rules depending on
+    /// specific dates such as holidays, date ranges or week numbers are only
+    /// honored as they apply to this week.
+    ///
+    /// Parameters
+    /// ----------
+    /// weekday : int
+    ///     Day of the week, from 0 for Monday to 6 for Sunday, as given by
+    ///     `datetime.weekday()`.
+    /// time : time
+    ///     Time of the day.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     The weekday is not between 0 and 6.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Tu 10:00-18:00").is_open_at(1, time(15, 0))
+    /// True
+    #[text_signature = "(self, weekday, time, /)"]
+    fn is_open_at(&self, weekday: u32, time: NaiveTimeWrapper) -> PyResult<bool> {
+        if weekday > 6 {
+            return Err(PyValueError::new_err("weekday must be between 0 and 6"));
+        }
+
+        let date = reference_week(self.clock) + Duration::days(weekday.into());
+        let time: NaiveTime = time.into();
+        Ok(self.inner.is_open(date.and_time(time)))
+    }
+
+    /// Get the comments attached to the state at a given time.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours('24/7 "on call"').comments_active_at()
+    /// ['on call']
+    /// >>> OpeningHours("24/7").comments_active_at()
+    /// []
+    #[text_signature = "(self, time=None, /, normalize_comments=False)"]
+    #[args(time = "None", normalize_comments = "false")]
+    fn comments_active_at(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        normalize_comments: bool,
+    ) -> Vec<String> {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        self.evaluate(time, |_, comments| {
+            copy_comments(comments.iter().map(String::as_str), normalize_comments)
+        })
+    }
+
+    /// Get the date for next change of state for each of a list of times.
+    ///
+    /// Parameters
+    /// ----------
+    /// times : List[datetime]
+    ///     Base times for the evaluation.
+    ///
+    /// Each element is `None` if the state never changes after the matching
+    /// time, as for `next_change`. Boundaries where only the comments change
+    /// are skipped.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").next_changes(
+    /// ...     [datetime(2020, 11, 2, 9), datetime(2020, 11, 2, 11)]
+    /// ... )
+    /// [datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0)]
+    #[text_signature = "(self, times, /)"]
+    fn next_changes(&self, times: Vec<NaiveDateTimeWrapper>) -> Vec<NaiveDateTimeWrapper> {
+        times
+            .into_iter()
+            .map(|time| {
+                self.next_state_change(time.into())
+                    .map(Into::into)
+                    .unwrap_or_else(NaiveDateTimeWrapper::max_py_value)
+            })
+            .collect()
+    }
+
+    /// Check if the state is open at any point of a time window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").is_open_during(
+    /// ...     datetime(2020, 11, 2, 11, 30), datetime(2020, 11, 2, 14)
+    /// ... )
+    /// True
+    #[text_signature = "(self, start, end, /)"]
+    fn is_open_during(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> bool {
+        self.iter_window(start.into(), end.into())
+            .any(|dt_range| dt_range.kind == RuleKind::Open)
+    }
+
+    /// Check if the state is open during a whole time window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// An empty window is never considered as open.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").is_open_throughout(
+    /// ...     datetime(2020, 11, 2, 11, 30), datetime(2020, 11, 2, 14)
+    /// ... )
+    /// False
+    #[text_signature = "(self, start, end, /)"]
+    fn is_open_throughout(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> bool {
+        start < end
+            && self
+                .iter_window(start.into(), end.into())
+                .all(|dt_range| dt_range.kind == RuleKind::Open)
+    }
+
+    /// Get a shallow copy of the object, as it is immutable the parsed
+    /// expression is shared with the original.
+    #[text_signature = "(self, /)"]
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    /// Get the arguments needed to rebuild the object when it is unpickled,
+    /// which is parsed again from its expression. As the expression is stored
+    /// after corrections were applied, the list of corrections is restored
+    /// through `__setstate__` along with the shift and the window.
+    #[text_signature = "(self, /)"]
+    fn __reduce__(
+        slf: PyRef<Self>,
+    ) -> PyResult<(
+        Py<PyAny>,
+        (String, bool, bool, usize, bool, &'static str),
+        Py<PyDict>,
+    )> {
+        let py = slf.py();
+        let max_length = max(slf.source.len(), DEFAULT_MAX_LENGTH);
+        let state = PyDict::new(py);
+        state.set_item("corrections", slf.corrections.clone())?;
+        domain_to_py(py, &slf.inner, state)?;
+
+        Ok((
+            py.get_type::<OpeningHours>().into_py(py),
+            (
+                slf.source.clone(),
+                false,
+                false,
+                max_length,
+                false,
+                slf.clock.as_str(),
+            ),
+            state.into(),
+        ))
+    }
+
+    /// Restore the corrections, the shift and the window of an unpickled
+    /// object.
+    ///
+    /// Parameters
+    /// ----------
+    /// state : dict
+    ///     State given by `__reduce__`.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     One of the corrections is not known, or the shift is out of range.
+    #[text_signature = "(self, state, /)"]
+    fn __setstate__(&mut self, state: &PyDict) -> PyResult<()> {
+        let corrections: Vec<String> = match state.get_item("corrections") {
+            Some(corrections) => corrections.extract()?,
+            None => Vec::new(),
+        };
+
+        self.corrections = corrections
+            .iter()
+            .map(|correction| {
+                fixes::CORRECTIONS
+                    .iter()
+                    .copied()
+                    .find(|known| *known == correction.as_str())
+                    .ok_or_else(|| {
+                        PyValueError::new_err(format!("unknown correction: {:?}", correction))
+                    })
+            })
+            .collect::<PyResult<_>>()?;
+
+        let domain = domain_from_py(Domain::new(self.inner.time_domain().clone()), state)?;
+        *self = self.with_domain(domain);
+        Ok(())
+    }
+
+    /// Get a copy of the object that owns its own copy of the parsed
+    /// expression.
+    #[text_signature = "(self, memo, /)"]
+    fn __deepcopy__(&self, _memo: &PyAny) -> Self {
+        self.with_domain(self.inner.as_ref().clone())
+    }
+
+    /// Get the open ranges of current week as tuples `(weekday, start, end)`
+    /// where `weekday` goes from 0 for Monday to 6 for Sunday.
+    ///
+    /// Ranges crossing midnight are split into one range for each day, a
+    /// range ending at midnight ends at `datetime.time.max`. Rules depending
+    /// on specific dates are only honored as they apply to current week.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00; Fr 22:00-02:00").open_ranges_as_tuples()
+    /// [(0, datetime.time(10, 0), datetime.time(12, 0)),
+    ///  (4, datetime.time(22, 0), datetime.time(23, 59, 59, 999999)),
+    ///  (5, datetime.time(0, 0), datetime.time(2, 0))]
+    #[text_signature = "(self, /)"]
+    fn open_ranges_as_tuples(&self) -> Vec<(u32, NaiveTimeWrapper, NaiveTimeWrapper)> {
+        self.week_open_ranges(reference_week(self.clock))
+    }
+
+    /// Get the state at current time, this is equivalent to `state()`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("24/7").state_now()
+    /// "open"
+    #[text_signature = "(self, /)"]
+    fn state_now(&self) -> State {
+        self.state(None, false)
+    }
+
+    /// Check if the state is open at current time, this is equivalent to
+    /// `is_open()`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("24/7").is_open_now()
+    /// True
+    #[text_signature = "(self, /)"]
+    fn is_open_now(&self) -> bool {
+        self.is_open(None, None, None)
+    }
+
+    /// Check if the state is closed at current time, this is equivalent to
+    /// `is_closed()`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("24/7 off").is_closed_now()
+    /// True
+    #[text_signature = "(self, /)"]
+    fn is_closed_now(&self) -> bool {
+        self.is_closed(None)
+    }
+
+    /// Check if the state is unknown at current time, this is equivalent to
+    /// `is_unknown()`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("24/7 unknown").is_unknown_now()
+    /// True
+    #[text_signature = "(self, /)"]
+    fn is_unknown_now(&self) -> bool {
+        self.is_unknown(None)
+    }
+
+    /// Get the boundaries of open ranges in a time window as a flat sorted
+    /// list `[start_1, end_1, start_2, end_2, ...]`.
+    ///
+    /// Contiguous open ranges are merged, thus a time is inside of an open
+    /// range if and only if `bisect.bisect_right(boundaries, time)` is odd.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00,14:00-18:00").boundaries(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3)
+    /// ... )
+    /// [datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0),
+    ///  datetime.datetime(2020, 11, 2, 14, 0), datetime.datetime(2020, 11, 2, 18, 0)]
+    #[text_signature = "(self, start, end, /)"]
+    fn boundaries(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> Vec<NaiveDateTimeWrapper> {
+        self.merged_open_ranges(start.into(), end.into())
+            .into_iter()
+            .flat_map(|range| vec![range.start.into(), range.end.into()])
+            .collect()
+    }
+
+    /// Check if the expression depends on solar events: dawn, sunrise,
+    /// sunset or dusk.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr sunrise-sunset").uses_solar_events()
+    /// True
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").uses_solar_events()
+    /// False
+    #[text_signature = "(self, /)"]
+    fn uses_solar_events(&self) -> bool {
+        selectors::uses_solar_events(self.inner.time_domain())
+    }
+
+    /// Check if the expression depends on public or school holidays, through
+    /// `PH` or `SH` selectors.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00; PH off").requires_holidays()
+    /// True
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").requires_holidays()
+    /// False
+    #[text_signature = "(self, /)"]
+    fn requires_holidays(&self) -> bool {
+        selectors::uses_holidays(self.inner.time_domain())
+    }
+
+    /// Get the dates of all changes of state in a time window.
+    ///
+    /// As for `changes`, boundaries between two ranges with the same state
+    /// are not reported.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").change_times(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3)
+    /// ... )
+    /// [datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0)]
+    #[text_signature = "(self, start, end, /)"]
+    fn change_times(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> Vec<NaiveDateTimeWrapper> {
+        state_changes(self.iter_window(start.into(), end.into()))
+            .skip(1)
+            .map(|dt_range| dt_range.range.start.into())
+            .collect()
+    }
+
+    /// Get the time of the first opening on a given day, or `None` if it is
+    /// closed all day.
+    ///
+    /// A range starting on the previous day and spilling over midnight opens
+    /// at `datetime.time(0, 0)`.
+    ///
+    /// Parameters
+    /// ----------
+    /// date : date
+    ///     Day to inspect.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00,14:00-18:00").earliest_open(date(2020, 11, 2))
+    /// datetime.time(10, 0)
+    #[text_signature = "(self, date, /)"]
+    fn earliest_open(&self, date: NaiveDateWrapper) -> Option<NaiveTimeWrapper> {
+        let day = day_range(date.into());
+        let first = self
+            .merged_open_ranges(day.start, day.end)
+            .into_iter()
+            .next()?;
+        Some(first.start.time().into())
+    }
+
+    /// Get the time of the last closing on a given day, or `None` if it is
+    /// closed all day.
+    ///
+    /// A range continuing past midnight is considered to close at the end of
+    /// the day, which is given as `datetime.time.max`.
+    ///
+    /// Parameters
+    /// ----------
+    /// date : date
+    ///     Day to inspect.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00,14:00-18:00").latest_close(date(2020, 11, 2))
+    /// datetime.time(18, 0)
+    #[text_signature = "(self, date, /)"]
+    fn latest_close(&self, date: NaiveDateWrapper) -> Option<NaiveTimeWrapper> {
+        let day = day_range(date.into());
+        let last = self.merged_open_ranges(day.start, day.end).pop()?;
+
+        Some({
+            if last.end == day.end {
+                NaiveTimeWrapper::end_of_day()
+            } else {
+                last.end.time().into()
+            }
+        })
+    }
+
+    /// Classify each day of a month as "open" if it is open all day, "closed"
+    /// if it is never open or "partial" otherwise.
+    ///
+    /// Parameters
+    /// ----------
+    /// year : int
+    ///     Year of the month.
+    /// month : int
+    ///     Month, from 1 to 12.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     The month doesn't exist.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00; Tu 00:00-24:00").month_status(2020, 11)[:3]
+    /// ['closed', 'partial', 'open']
+    #[text_signature = "(self, year, month, /)"]
+    fn month_status(&self, year: i32, month: u32) -> PyResult<Vec<&'static str>> {
+        let first_day = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| PyValueError::new_err("invalid year or month"))?;
+
+        let next_month_first_day = {
+            if month == 12 {
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd_opt(year, month + 1, 1)
+            }
+        }
+        .ok_or_else(|| PyValueError::new_err("invalid year or month"))?;
+
+        let days_count = (next_month_first_day - first_day).num_days() as usize;
+        let mut open_durations = vec![Duration::zero(); days_count];
+
+        for range in self.merged_open_ranges(
+            first_day.and_hms(0, 0, 0),
+            next_month_first_day.and_hms(0, 0, 0),
+        ) {
+            for day_range in split_by_day(range) {
+                let day = (day_range.start.date() - first_day).num_days() as usize;
+                open_durations[day] += day_range.end - day_range.start;
+            }
+        }
+
+        Ok(open_durations
+            .into_iter()
+            .map(|duration| {
+                if duration == Duration::zero() {
+                    "closed"
+                } else if duration == Duration::days(1) {
+                    "open"
+                } else {
+                    "partial"
+                }
+            })
+            .collect())
+    }
+
+    /// Get the state as an integer code: 0 for closed, 1 for open and 2 for
+    /// unknown.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("24/7").state_code()
+    /// 1
+    #[text_signature = "(self, time=None, /)"]
+    fn state_code(&self, time: Option<NaiveDateTimeWrapper>) -> u8 {
+        self.state(time, false).code()
+    }
+
+    /// Get the state code, as given by `state_code`, for each of a list of
+    /// times.
+    ///
+    /// Parameters
+    /// ----------
+    /// times : List[datetime]
+    ///     Base times for the evaluation.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").states_codes(
+    /// ...     [datetime(2020, 11, 2, 9), datetime(2020, 11, 2, 11)]
+    /// ... )
+    /// [0, 1]
+    #[text_signature = "(self, times, /)"]
+    fn states_codes(&self, times: Vec<NaiveDateTimeWrapper>) -> Vec<u8> {
+        times
+            .into_iter()
+            .map(|time| State::from(self.inner.state(time.into())).code())
+            .collect()
+    }
+
+    /// Get opening hours where the rules of `other` override the rules of
+    /// this expression, as if they were appended to it.
+    ///
+    /// Overrides apply per day, following the semantics of rules in an
+    /// expression: on days matched by a rule of `other`, only rules of
+    /// `other` apply, other days keep the rules of this expression.
+    ///
+    /// Parameters
+    /// ----------
+    /// other : OpeningHours
+    ///     Opening hours taking precedence where they are defined.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If either of the opening hours is shifted or restricted, which
+    ///     can't be expressed with rules.
+    ///
+    /// Examples
+    /// --------
+    /// >>> regular = OpeningHours("Mo-Fr 10:00-18:00")
+    /// >>> exception = OpeningHours("Mo 14:00-16:00")
+    /// >>> regular.overlay(exception).is_open(datetime(2020, 11, 2, 11))
+    /// False
+    /// >>> regular.overlay(exception).is_open(datetime(2020, 11, 3, 11))
+    /// True
+    #[text_signature = "(self, other, /)"]
+    fn overlay(&self, other: &OpeningHours) -> PyResult<Self> {
+        for domain in &[&self.inner, &other.inner] {
+            if domain.shift() != Duration::zero() || domain.window().is_some() {
+                return Err(PyValueError::new_err(
+                    "shifted or restricted opening hours can't be overlaid",
+                ));
+            }
+        }
+
+        let mut td = self.inner.time_domain().clone();
+        td.rules
+            .extend(other.inner.time_domain().rules.iter().cloned());
+
+        let source = format!("{}; {}", self.source, other.source);
+
+        Ok(Self {
+            clock: self.clock,
+            ..Self::from_domain(Domain::new(td), source)
+        })
+    }
+
+    /// Summarize the state over a time window: whether it is fully open,
+    /// fully closed, fully unknown or mixed, along with the comments attached
+    /// to any part of the window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces, comments that
+    ///     only differ by their whitespaces are then only given once.
+    ///
+    /// An empty window is summarized as "closed" without any comment.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours('Mo 10:00-12:00 "lunch break after"').summarize(
+    /// ...     datetime(2020, 11, 2, 11, 30), datetime(2020, 11, 2, 14)
+    /// ... )
+    /// Summary(state="mixed", comments=["lunch break after"])
+    #[text_signature = "(self, start, end, /, normalize_comments=False)"]
+    #[args(normalize_comments = "false")]
+    fn summarize(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+        normalize_comments: bool,
+    ) -> Summary {
+        let mut state = None;
+        let mut mixed = false;
+        let mut comments: Vec<String> = Vec::new();
+
+        for dt_range in self.iter_window(start.into(), end.into()) {
+            let range_state = State::from(dt_range.kind);
+            mixed = mixed || state.map(|state| state != range_state).unwrap_or(false);
+            state = Some(range_state);
+
+            for comment in copy_comments(dt_range.comments, normalize_comments) {
+                if !comments.contains(&comment) {
+                    comments.push(comment);
+                }
+            }
+        }
+
+        Summary {
+            state: if mixed {
+                "mixed"
+            } else {
+                state.unwrap_or(State::Closed).as_str()
+            },
+            comments,
+        }
+    }
+
+    /// Get the change of state that is the closest to a given time, either
+    /// before or after it.
+    ///
+    /// Returns a tuple `(at, direction)` where direction is either "previous"
+    /// or "next", or `None` if the state never changes. When both changes
+    /// are at the same distance, the next one is preferred. Boundaries where
+    /// only the comments change are not changes of state.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00").closest_change(datetime(2020, 11, 2, 11))
+    /// (datetime.datetime(2020, 11, 2, 10, 0), 'previous')
+    #[text_signature = "(self, time=None, /)"]
+    fn closest_change(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+    ) -> Option<(NaiveDateTimeWrapper, &'static str)> {
+        let time = get_time(time.map(Into::into), self.clock);
+        let prev = self.prev_change(time);
+
+        let next = self.next_state_change(time).filter(|next| {
+            NaiveDateTimeWrapper::from(*next) < NaiveDateTimeWrapper::max_py_value()
+        });
+
+        match (prev, next) {
+            (Some(prev), Some(next)) if time - prev < next - time => {
+                Some((prev.into(), "previous"))
+            }
+            (_, Some(next)) => Some((next.into(), "next")),
+            (Some(prev), None) => Some((prev.into(), "previous")),
+            (None, None) => None,
+        }
+    }
+
+    /// Get the bounds `(start, end)` of the open range containing a given
+    /// time, or `None` if the state is not open at this time.
+    ///
+    /// Contiguous open ranges that only differ by their comments are part of
+    /// the same range. The start is `None` if the state didn't change for the
+    /// last ten years and the end is `None` if the range never ends.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00").current_range(datetime(2020, 11, 2, 11))
+    /// (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 18, 0))
+    #[text_signature = "(self, time=None, /)"]
+    fn current_range(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+    ) -> Option<(Option<NaiveDateTimeWrapper>, NaiveDateTimeWrapper)> {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        if !self.inner.is_open(time) {
+            return None;
+        }
+
+        let end = self
+            .next_state_change(time)
+            .unwrap_or_else(|| NaiveDateTimeWrapper::max_py_value().into());
+
+        Some((self.prev_change(time).map(Into::into), end.into()))
+    }
+
+    /// Get the last `n` intervals of consistent state before a given time,
+    /// in chronological order.
+    ///
+    /// The last interval is clipped to end at `before`. Search doesn't go
+    /// further than ten years back, in which case the first interval is also
+    /// clipped.
+    ///
+    /// Parameters
+    /// ----------
+    /// n : int
+    ///     Maximal number of intervals to return.
+    /// before : Optional[datetime]
+    ///     End of the search, current time will be used if it is not
+    ///     specified.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> [state for _, _, state, _ in OpeningHours("Mo 10:00-18:00").last_intervals(
+    /// ...     2, datetime(2020, 11, 2, 11)
+    /// ... )]
+    /// ['closed', 'open']
+    #[text_signature = "(self, n, /, before=None, normalize_comments=False)"]
+    #[args(before = "None", normalize_comments = "false")]
+    fn last_intervals(
+        &self,
+        n: usize,
+        before: Option<NaiveDateTimeWrapper>,
+        normalize_comments: bool,
+    ) -> Vec<IntervalTuple> {
+        let before = get_time(before.map(Into::into), self.clock);
+        let mut lookback = Duration::days(7);
+
+        loop {
+            let ranges: Vec<_> = self
+                .iter_window(add_clamped(before, -lookback), before)
+                .collect();
+
+            if ranges.len() > n || lookback >= Duration::days(MAX_LOOKBACK_DAYS) {
+                let skipped = ranges.len().saturating_sub(n);
+                return ranges
+                    .into_iter()
+                    .skip(skipped)
+                    .map(|dt_range| interval_tuple(dt_range, normalize_comments))
+                    .collect();
+            }
+
+            lookback = min(lookback * 2, Duration::days(MAX_LOOKBACK_DAYS));
+        }
+    }
+
+    /// Get the first day that has an open range, starting from a given day.
+    ///
+    /// Parameters
+    /// ----------
+    /// from_date : Optional[date]
+    ///     First day to inspect, current day will be used if it is not
+    ///     specified.
+    /// horizon : int
+    ///     Maximal number of days to inspect, `None` is returned if none of
+    ///     them has an open range.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("We 10:00-18:00").next_open_day(date(2020, 11, 2))
+    /// datetime.date(2020, 11, 4)
+    #[text_signature = "(self, from_date=None, /, horizon=366)"]
+    #[args(from_date = "None", horizon = "366")]
+    fn next_open_day(
+        &self,
+        from_date: Option<NaiveDateWrapper>,
+        horizon: u32,
+    ) -> Option<NaiveDateWrapper> {
+        let from_date = from_date
+            .map(Into::into)
+            .unwrap_or_else(|| get_time(None, self.clock).date());
+
+        // Days after the last one supported by Python are never reached.
+        (0..horizon)
+            .map_while(|offset| {
+                from_date
+                    .checked_add_signed(Duration::days(offset.into()))
+                    .filter(|date| date.year() <= 9999)
+            })
+            .find(|&date| {
+                let day = day_range(date);
+                !self.merged_open_ranges(day.start, day.end).is_empty()
+            })
+            .map(Into::into)
+    }
+
+    /// Count the open periods in a time window, contiguous open ranges with
+    /// different comments count as a single period.
+    ///
+    /// Unlike `total_changes`, closed and unknown ranges are not counted.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-12:00,14:00-18:00").open_window_count(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 9)
+    /// ... )
+    /// 10
+    #[text_signature = "(self, start, end, /)"]
+    fn open_window_count(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> usize {
+        self.merged_open_ranges(start.into(), end.into()).len()
+    }
+
+    /// Count the changes of state in a time window, grouped by the states
+    /// before and after the change. Pairs of states are listed in order of
+    /// their first change.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").transition_counts(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 9)
+    /// ... )
+    /// {('closed', 'open'): 5, ('open', 'closed'): 5}
+    #[text_signature = "(self, start, end, /)"]
+    fn transition_counts(
+        &self,
+        py: Python,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> PyResult<PyObject> {
+        let timeline = self.state_timeline(start.into(), end.into());
+        let mut counts: Vec<((State, State), usize)> = Vec::new();
+
+        for pair in timeline.windows(2) {
+            let transition = (State::from(pair[0].1), State::from(pair[1].1));
+
+            match counts.iter_mut().find(|(other, _)| *other == transition) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((transition, 1)),
+            }
+        }
+
+        let py_counts = PyDict::new(py);
+
+        for ((from_state, to_state), count) in counts {
+            py_counts.set_item((from_state.as_str(), to_state.as_str()), count)?;
+        }
+
+        Ok(py_counts.into())
+    }
+
+    /// Check if the state is open for each of a list of times.
+    ///
+    /// Parameters
+    /// ----------
+    /// times : List[datetime]
+    ///     Base times for the evaluation.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00").are_open(
+    /// ...     [datetime(2020, 11, 2, 9), datetime(2020, 11, 2, 11)]
+    /// ... )
+    /// [False, True]
+    #[text_signature = "(self, times, /)"]
+    fn are_open(&self, times: Vec<NaiveDateTimeWrapper>) -> Vec<bool> {
+        times
+            .into_iter()
+            .map(|time| self.inner.is_open(time.into()))
+            .collect()
+    }
+
+    /// Map the comments active at a given time to a category, the category
+    /// of the first active comment found in `rules` is returned.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// rules : Dict[str, str]
+    ///     Category associated with each comment.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours('Mo 10:00-12:00 "by appointment"').classify(
+    /// ...     datetime(2020, 11, 2, 11), rules={"by appointment": "warning"}
+    /// ... )
+    /// 'warning'
+    #[text_signature = "(self, time=None, /, rules={})"]
+    #[args(time = "None", rules = "HashMap::new()")]
+    fn classify(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        rules: HashMap<String, String>,
+    ) -> Option<String> {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        self.evaluate(time, |_, comments| {
+            comments
+                .iter()
+                .find_map(|comment| rules.get(comment))
+                .cloned()
+        })
+    }
+
+    /// Get the open periods of a time window as tuples `(start, end)`,
+    /// contiguous open ranges with different comments are merged together.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    /// merge_gaps : Optional[timedelta]
+    ///     Also merge open periods separated by a gap shorter than this
+    ///     duration, such as a lunch break. Gaps that are not entirely
+    ///     closed, for example with an unknown state, are never merged.
+    /// with_duration : bool
+    ///     Give tuples `(start, end, duration)` with the duration of each
+    ///     period as a third item.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00,13:00-18:00").opening_intervals(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3), merge_gaps=timedelta(hours=2)
+    /// ... )
+    /// [(datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 18, 0))]
+    ///
+    /// >>> OpeningHours("Mo 10:00-12:00").opening_intervals(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3), with_duration=True
+    /// ... )
+    /// [(datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0), datetime.timedelta(seconds=7200))]
+    #[text_signature = "(self, start, end, /, merge_gaps=None, with_duration=False)"]
+    #[args(merge_gaps = "None", with_duration = "false")]
+    fn opening_intervals(
+        &self,
+        py: Python,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+        merge_gaps: Option<DurationWrapper>,
+        with_duration: bool,
+    ) -> Vec<PyObject> {
+        let mut result: Vec<Range<NaiveDateTime>> = Vec::new();
+
+        for range in self.merged_open_ranges(start.into(), end.into()) {
+            match (result.last_mut(), merge_gaps) {
+                (Some(last), Some(gap))
+                    if range.start - last.end < gap.into()
+                        && self
+                            .iter_window(last.end, range.start)
+                            .all(|dt_range| dt_range.kind == RuleKind::Closed) =>
+                {
+                    last.end = range.end
+                }
+                _ => result.push(range),
+            }
+        }
+
+        result
+            .into_iter()
+            .map(|range| {
+                let bounds: (NaiveDateTimeWrapper, NaiveDateTimeWrapper) =
+                    (range.start.into(), range.end.into());
+
+                if with_duration {
+                    let duration = DurationWrapper::from(range.end - range.start);
+                    (bounds.0, bounds.1, duration).into_py(py)
+                } else {
+                    bounds.into_py(py)
+                }
+            })
+            .collect()
+    }
+
+    /// Get a reason for the state not being open at a given time, or `None`
+    /// if it is open.
+    ///
+    /// The comments attached to the current state are used as the reason if
+    /// there are any, otherwise a reason is built from the state and the date
+    /// of the next change.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours('Mo 10:00-18:00; Tu off "inventory"').closed_reason(
+    /// ...     datetime(2020, 11, 3, 11)
+    /// ... )
+    /// 'inventory'
+    ///
+    /// >>> OpeningHours("Mo 10:00-18:00").closed_reason(datetime(2020, 11, 2, 8))
+    /// 'closed until 2020-11-02 10:00'
+    #[text_signature = "(self, time=None, /)"]
+    fn closed_reason(&self, time: Option<NaiveDateTimeWrapper>) -> Option<String> {
+        let time = get_time(time.map(Into::into), self.clock);
+
+        let (state, comments) = self.evaluate(time, |kind, comments| {
+            (State::from(kind), comments.join("; "))
+        });
+
+        if state == State::Open {
+            return None;
+        }
+
+        if !comments.is_empty() {
+            return Some(comments);
+        }
+
+        let next = self.next_state_change(time).filter(|next| {
+            NaiveDateTimeWrapper::from(*next) < NaiveDateTimeWrapper::max_py_value()
+        });
+
+        Some(match next {
+            Some(next) => format!("{} until {}", state.as_str(), next.format("%Y-%m-%d %H:%M")),
+            None => state.as_str().to_string(),
+        })
+    }
+
+    /// Give an iterator that yields lists of up to `batch_size` successive
+    /// intervals, as given by `intervals`.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : Optional[datetime]
+    ///     Initial time for the iterator, current time will be used if it is
+    ///     not specified or `None`. Note that this differs from `intervals`,
+    ///     where an explicit `None` stands for `datetime.min`.
+    /// end : Optional[datetime]
+    ///     Maximal time for the iterator, the iterator will continue until
+    ///     year 9999 if it no max is specified.
+    /// batch_size : int
+    ///     Maximal number of intervals in each list.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     Given batch size is zero.
+    ///
+    /// Examples
+    /// --------
+    /// >>> batches = OpeningHours("Mo-Fr 10:00-18:00").intervals_batched(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 9), batch_size=4
+    /// ... )
+    /// >>> [len(batch) for batch in batches]
+    /// [4, 4, 3]
+    #[text_signature = "(self, start=None, end=None, /, batch_size=1024)"]
+    #[args(start = "None", end = "None", batch_size = "1024")]
+    fn intervals_batched(
         &self,
         start: Option<NaiveDateTimeWrapper>,
         end: Option<NaiveDateTimeWrapper>,
-    ) -> RangeIterator {
-        RangeIterator::new(
+        batch_size: usize,
+    ) -> PyResult<BatchIterator> {
+        if batch_size == 0 {
+            return Err(PyValueError::new_err("batch_size must be positive"));
+        }
+
+        Ok(BatchIterator::new(
+            RangeIterator::new(
+                self.inner.clone(),
+                get_time(start.map(Into::into), self.clock),
+                end.map(Into::into),
+            ),
+            batch_size,
+        ))
+    }
+
+    /// Get the date of the next opening after a given time, or `None` if it
+    /// doesn't open within `horizon` days.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// weekday : Optional[int]
+    ///     Only consider openings on this day of the week, from 0 for Monday
+    ///     to 6 for Sunday.
+    /// horizon : int
+    ///     Maximal number of days to search.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     Given weekday is not between 0 and 6.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo,We 10:00-18:00").next_open(datetime(2020, 11, 2, 12), weekday=0)
+    /// datetime.datetime(2020, 11, 9, 10, 0)
+    #[text_signature = "(self, time=None, /, weekday=None, horizon=366)"]
+    #[args(time = "None", weekday = "None", horizon = "366")]
+    fn next_open(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        weekday: Option<u32>,
+        horizon: u32,
+    ) -> PyResult<Option<NaiveDateTimeWrapper>> {
+        if weekday.map_or(false, |weekday| weekday > 6) {
+            return Err(PyValueError::new_err("weekday must be between 0 and 6"));
+        }
+
+        let time = get_time(time.map(Into::into), self.clock);
+
+        Ok(self
+            .next_period_start(RuleKind::Open, time, weekday, horizon)
+            .map(Into::into))
+    }
+
+    /// Get the date of the next closing after a given time, or `None` if it
+    /// doesn't close within `horizon` days.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// weekday : Optional[int]
+    ///     Only consider closings on this day of the week, from 0 for Monday
+    ///     to 6 for Sunday.
+    /// horizon : int
+    ///     Maximal number of days to search.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     Given weekday is not between 0 and 6.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00").next_closed(datetime(2020, 11, 2, 12))
+    /// datetime.datetime(2020, 11, 2, 18, 0)
+    #[text_signature = "(self, time=None, /, weekday=None, horizon=366)"]
+    #[args(time = "None", weekday = "None", horizon = "366")]
+    fn next_closed(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        weekday: Option<u32>,
+        horizon: u32,
+    ) -> PyResult<Option<NaiveDateTimeWrapper>> {
+        if weekday.map_or(false, |weekday| weekday > 6) {
+            return Err(PyValueError::new_err("weekday must be between 0 and 6"));
+        }
+
+        let time = get_time(time.map(Into::into), self.clock);
+
+        Ok(self
+            .next_period_start(RuleKind::Closed, time, weekday, horizon)
+            .map(Into::into))
+    }
+
+    /// Get the total time the state is open in a time window, ranges
+    /// overlapping the edges of the window are truncated.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    /// daily_window : Optional[Tuple[time, time]]
+    ///     Only count open time between these clock times of each day.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If `daily_window` doesn't start before it ends.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00").open_duration(
+    /// ...     datetime(2020, 11, 2, 16), datetime(2020, 11, 3)
+    /// ... )
+    /// datetime.timedelta(seconds=7200)
+    ///
+    /// >>> OpeningHours("Mo 06:00-18:00").open_duration(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 3),
+    /// ...     daily_window=(time(8), time(20)),
+    /// ... )
+    /// datetime.timedelta(seconds=36000)
+    #[text_signature = "(self, start, end, /, daily_window=None)"]
+    #[args(daily_window = "None")]
+    fn open_duration(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+        daily_window: Option<(NaiveTimeWrapper, NaiveTimeWrapper)>,
+    ) -> PyResult<DurationWrapper> {
+        let window = self::daily_window(daily_window)?;
+
+        Ok(self
+            .duration_of(RuleKind::Open, start.into(), end.into(), window)
+            .into())
+    }
+
+    /// Get the total time the state is closed in a time window, ranges
+    /// overlapping the edges of the window are truncated.
+    ///
+    /// Time with an unknown state is neither counted as open nor closed, use
+    /// `state_breakdown` to get the duration of each state at once.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-18:00").closed_duration(
+    /// ...     datetime(2020, 11, 2, 16), datetime(2020, 11, 3)
+    /// ... )
+    /// datetime.timedelta(seconds=21600)
+    #[text_signature = "(self, start, end, /)"]
+    fn closed_duration(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> DurationWrapper {
+        self.duration_of(RuleKind::Closed, start.into(), end.into(), None)
+            .into()
+    }
+
+    /// Get the open ranges overlapping a given day as tuples `(start, end)`
+    /// of datetimes, ranges spilling over midnight are not truncated.
+    ///
+    /// Contiguous open ranges with different comments are merged together,
+    /// and ranges are not followed further than a day before or after the
+    /// given day.
+    ///
+    /// Parameters
+    /// ----------
+    /// date : date
+    ///     Day to inspect.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 20:00-02:00").open_on(date(2020, 11, 2))
+    /// [(datetime.datetime(2020, 11, 2, 20, 0), datetime.datetime(2020, 11, 3, 2, 0))]
+    #[text_signature = "(self, date, /)"]
+    fn open_on(&self, date: NaiveDateWrapper) -> Vec<(NaiveDateTimeWrapper, NaiveDateTimeWrapper)> {
+        let day = day_range(date.into());
+
+        self.merged_open_ranges(day.start - Duration::days(1), day.end + Duration::days(1))
+            .into_iter()
+            .filter(|range| range.start < day.end && range.end > day.start)
+            .map(|range| (range.start.into(), range.end.into()))
+            .collect()
+    }
+
+    /// Get the comments that will be attached to the state after the next
+    /// change, or `None` if the state never changes.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours('Mo 10:00-18:00 "summer hours"').next_comments(
+    /// ...     datetime(2020, 11, 2, 8)
+    /// ... )
+    /// ['summer hours']
+    #[text_signature = "(self, time=None, /, normalize_comments=False)"]
+    #[args(time = "None", normalize_comments = "false")]
+    fn next_comments(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        normalize_comments: bool,
+    ) -> Option<Vec<String>> {
+        let next = self
+            .inner
+            .iter_from(get_time(time.map(Into::into), self.clock))
+            .nth(1)?;
+
+        Some(copy_comments(next.comments, normalize_comments))
+    }
+
+    /// Get the fraction of each day of a time window during which the state
+    /// is open, as a list of tuples `(date, fraction)`.
+    ///
+    /// Days on the edges of the window are only partially covered, their
+    /// fraction is relative to the part of the day inside of the window.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 06:00-18:00").open_fraction_by_day(
+    /// ...     datetime(2020, 11, 2), datetime(2020, 11, 4)
+    /// ... )
+    /// [(datetime.date(2020, 11, 2), 0.5), (datetime.date(2020, 11, 3), 0.0)]
+    #[text_signature = "(self, start, end, /)"]
+    fn open_fraction_by_day(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+    ) -> Vec<(NaiveDateWrapper, f64)> {
+        let (start, end): (NaiveDateTime, NaiveDateTime) = (start.into(), end.into());
+
+        if start >= end {
+            return Vec::new();
+        }
+
+        let first_day = start.date();
+        let mut open_time: Vec<Duration> =
+            split_by_day(start..end).map(|_| Duration::zero()).collect();
+
+        for day_range in self
+            .merged_open_ranges(start, end)
+            .into_iter()
+            .flat_map(split_by_day)
+        {
+            let index = (day_range.start.date() - first_day).num_days() as usize;
+            open_time[index] += day_range.end - day_range.start;
+        }
+
+        split_by_day(start..end)
+            .zip(open_time)
+            .map(|(day, open)| {
+                let fraction = open.num_milliseconds() as f64
+                    / (day.end - day.start).num_milliseconds() as f64;
+                (day.start.date().into(), fraction)
+            })
+            .collect()
+    }
+
+    /// Get cron expressions matching the moments when the state becomes
+    /// open, or `None` if the schedule can't be reduced to a weekly schedule.
+    ///
+    /// Supported expressions are the ones that give the same open ranges
+    /// every week during the year following current week, and don't depend
+    /// on holidays, solar events or a window set by `restrict`. Each
+    /// expression is formatted as `minute hour * * weekdays`, with weekdays
+    /// numbered from 0 for Sunday.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo-Fr 10:00-18:00; Sa 09:30-12:00").to_cron()
+    /// ['30 9 * * 6', '0 10 * * 1,2,3,4,5']
+    #[text_signature = "(self, /)"]
+    fn to_cron(&self) -> Option<Vec<String>> {
+        let td = self.inner.time_domain();
+
+        // Restricted opening hours don't repeat outside of their window.
+        if self.inner.window().is_some()
+            || selectors::uses_holidays(td)
+            || selectors::uses_solar_events(td)
+        {
+            return None;
+        }
+
+        let week_start = reference_week(self.clock);
+        let week = self.week_open_ranges(week_start);
+
+        let is_weekly = (1..53)
+            .all(|offset| self.week_open_ranges(week_start + Duration::weeks(offset)) == week);
+
+        if !is_weekly {
+            return None;
+        }
+
+        let window_start = (week_start - Duration::days(1)).and_hms(0, 0, 0);
+        let window_end = (week_start + Duration::days(7)).and_hms(0, 0, 0);
+        let mut weekdays_by_time: BTreeMap<(u32, u32), Vec<String>> = BTreeMap::new();
+
+        for range in self.merged_open_ranges(window_start, window_end) {
+            if range.start.date() < week_start {
+                continue;
+            }
+
+            weekdays_by_time
+                .entry((range.start.hour(), range.start.minute()))
+                .or_default()
+                .push(range.start.weekday().num_days_from_sunday().to_string());
+        }
+
+        Some(
+            weekdays_by_time
+                .into_iter()
+                .map(|((hour, minute), weekdays)| {
+                    format!("{} {} * * {}", minute, hour, weekdays.join(","))
+                })
+                .collect(),
+        )
+    }
+
+    /// Get an iterator over the schedule of each ISO week overlapping a time
+    /// window, as tuples `(monday, schedule)`.
+    ///
+    /// The schedule maps each weekday, from 0 for Monday to 6 for Sunday, to
+    /// the open ranges of this day as given by `open_ranges_as_tuples`. Weeks
+    /// are always described in full, even if they are only partially covered
+    /// by the window. Each week is only computed when the iterator reaches
+    /// it.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    ///
+    /// Examples
+    /// --------
+    /// >>> list(OpeningHours("Mo 10:00-12:00").iter_weeks(
+    /// ...     datetime(2020, 11, 4), datetime(2020, 11, 10)
+    /// ... ))
+    /// [(datetime.date(2020, 11, 2), {0: [(datetime.time(10, 0), datetime.time(12, 0))], 1: [], ...}),
+    ///  (datetime.date(2020, 11, 9), {0: [(datetime.time(10, 0), datetime.time(12, 0))], 1: [], ...})]
+    #[text_signature = "(self, start, end, /)"]
+    fn iter_weeks(&self, start: NaiveDateTimeWrapper, end: NaiveDateTimeWrapper) -> WeekIterator {
+        let (start, end): (NaiveDateTime, NaiveDateTime) = (start.into(), end.into());
+        let oh = self.clone();
+
+        let mut week_start =
+            start.date() - Duration::days(start.weekday().num_days_from_monday().into());
+
+        WeekIterator::new(std::iter::from_fn(move || {
+            if start >= end || week_start.and_hms(0, 0, 0) >= end {
+                return None;
+            }
+
+            let mut schedule: BTreeMap<_, _> =
+                (0..7).map(|weekday| (weekday, Vec::new())).collect();
+
+            for (weekday, range_start, range_end) in oh.week_open_ranges(week_start) {
+                if let Some(ranges) = schedule.get_mut(&weekday) {
+                    ranges.push((range_start, range_end));
+                }
+            }
+
+            let week = (week_start.into(), schedule);
+            week_start += Duration::weeks(1);
+            Some(week)
+        }))
+    }
+
+    /// Get the open period that is the closest to a given time as a tuple
+    /// `(start, end)`, or `None` if there is no open period within `horizon`
+    /// days before or after it.
+    ///
+    /// If the state is open at given time, the current open period is
+    /// returned. Contiguous open ranges with different comments are merged
+    /// together and periods are truncated to the search window.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// direction : str
+    ///     Either "nearest" to search in both directions, preferring the next
+    ///     period in case of a tie, or "next" or "previous" to only search in
+    ///     one direction.
+    /// horizon : int
+    ///     Maximal number of days to search in each direction.
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     Given direction is not supported.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 10:00-12:00,20:00-22:00").nearest_open_range(
+    /// ...     datetime(2020, 11, 2, 13)
+    /// ... )
+    /// (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0))
+    #[text_signature = "(self, time=None, /, direction='nearest', horizon=366)"]
+    #[args(time = "None", direction = "\"nearest\"", horizon = "366")]
+    fn nearest_open_range(
+        &self,
+        time: Option<NaiveDateTimeWrapper>,
+        direction: &str,
+        horizon: u32,
+    ) -> PyResult<Option<(NaiveDateTimeWrapper, NaiveDateTimeWrapper)>> {
+        let (search_prev, search_next) = match direction {
+            "nearest" => (true, true),
+            "next" => (false, true),
+            "previous" => (true, false),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unsupported value for direction: {:?}",
+                    direction
+                )))
+            }
+        };
+
+        let time = get_time(time.map(Into::into), self.clock);
+        let horizon = Duration::days(horizon.into());
+        let ranges =
+            self.merged_open_ranges(add_clamped(time, -horizon), add_clamped(time, horizon));
+
+        let current = ranges
+            .iter()
+            .find(|range| range.start <= time && time < range.end);
+
+        let prev = ranges
+            .iter()
+            .rev()
+            .find(|range| range.end <= time)
+            .filter(|_| search_prev);
+
+        let next = ranges
+            .iter()
+            .find(|range| range.start > time)
+            .filter(|_| search_next);
+
+        let nearest = match (current, prev, next) {
+            (Some(current), _, _) => Some(current),
+            (None, Some(prev), Some(next)) if time - prev.end < next.start - time => Some(prev),
+            (None, prev, next) => next.or(prev),
+        };
+
+        Ok(nearest.map(|range| (range.start.into(), range.end.into())))
+    }
+
+    /// Render the open ranges of a week as a table with one line for each
+    /// day of the week.
+    ///
+    /// Parameters
+    /// ----------
+    /// week_of : Optional[date]
+    ///     Any day of the week to render, current week will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> print(OpeningHours("Mo-Fr 10:00-12:00,14:00-18:00; Sa 10:00-24:00").pretty())
+    /// Mo | 10:00-12:00, 14:00-18:00
+    /// Tu | 10:00-12:00, 14:00-18:00
+    /// We | 10:00-12:00, 14:00-18:00
+    /// Th | 10:00-12:00, 14:00-18:00
+    /// Fr | 10:00-12:00, 14:00-18:00
+    /// Sa | 10:00-24:00
+    /// Su | closed
+    #[text_signature = "(self, week_of=None, /)"]
+    fn pretty(&self, week_of: Option<NaiveDateWrapper>) -> String {
+        const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+        let week_start = match week_of {
+            Some(date) => {
+                let date: NaiveDate = date.into();
+                date - Duration::days(date.weekday().num_days_from_monday().into())
+            }
+            None => reference_week(self.clock),
+        };
+
+        let mut days = vec![Vec::new(); 7];
+
+        for (weekday, start, end) in self.week_open_ranges(week_start) {
+            let start: NaiveTime = start.into();
+
+            let end = {
+                if end == NaiveTimeWrapper::end_of_day() {
+                    "24:00".to_string()
+                } else {
+                    let end: NaiveTime = end.into();
+                    end.format("%H:%M").to_string()
+                }
+            };
+
+            days[weekday as usize].push(format!("{}-{}", start.format("%H:%M"), end));
+        }
+
+        WEEKDAYS
+            .iter()
+            .zip(days)
+            .map(|(name, ranges)| {
+                if ranges.is_empty() {
+                    format!("{} | closed", name)
+                } else {
+                    format!("{} | {}", name, ranges.join(", "))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Get the intervals of consistent state in a time window grouped by
+    /// day, as a dict mapping each date to a list of tuples `(start, end,
+    /// state, comments)`.
+    ///
+    /// Intervals spanning over midnight are split so that each part is
+    /// listed in the bucket of its own day, intervals overlapping the edges
+    /// of the window are truncated.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Start of the window.
+    /// end : datetime
+    ///     End of the window, which is excluded.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> OpeningHours("Mo 20:00-02:00").intervals_by_day(
+    /// ...     datetime(2020, 11, 2, 18), datetime(2020, 11, 3, 4)
+    /// ... )
+    /// {datetime.date(2020, 11, 2): [(datetime.datetime(2020, 11, 2, 18, 0), datetime.datetime(2020, 11, 2, 20, 0), 'closed', []),
+    ///                               (datetime.datetime(2020, 11, 2, 20, 0), datetime.datetime(2020, 11, 3, 0, 0), 'open', [])],
+    ///  datetime.date(2020, 11, 3): [(datetime.datetime(2020, 11, 3, 0, 0), datetime.datetime(2020, 11, 3, 2, 0), 'open', []),
+    ///                               (datetime.datetime(2020, 11, 3, 2, 0), datetime.datetime(2020, 11, 3, 4, 0), 'closed', [])]}
+    #[text_signature = "(self, start, end, /, normalize_comments=False)"]
+    #[args(normalize_comments = "false")]
+    fn intervals_by_day(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: NaiveDateTimeWrapper,
+        normalize_comments: bool,
+    ) -> BTreeMap<NaiveDateWrapper, Vec<IntervalTuple>> {
+        let mut by_day: BTreeMap<NaiveDateWrapper, Vec<IntervalTuple>> = BTreeMap::new();
+
+        for dt_range in self.iter_window(start.into(), end.into()) {
+            for day_range in split_by_day(dt_range.range.clone()) {
+                let mut part = dt_range.clone();
+                part.range = day_range;
+
+                by_day
+                    .entry(part.range.start.date().into())
+                    .or_default()
+                    .push(interval_tuple(part, normalize_comments));
+            }
+        }
+
+        by_day
+    }
+
+    /// Get a short description of the current state and of the next change,
+    /// such as `"Open · closes 18:00"` or `"Closed · opens Mon 09:00"`.
+    ///
+    /// The time of the next change is given alone if it happens the same
+    /// day, along with the weekday if it happens during the following week
+    /// and with the full date otherwise.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Base time for the evaluation, current time will be used if it is
+    ///     not specified.
+    /// locale : str
+    ///     Language of the description, either "en" or "fr".
+    ///
+    /// Raises
+    /// ------
+    /// ValueError
+    ///     If `locale` is not supported.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo-Fr 09:00-18:00")
+    /// >>> oh.status_line(datetime(2020, 11, 2, 12))
+    /// 'Open · closes 18:00'
+    ///
+    /// >>> oh.status_line(datetime(2020, 11, 7, 12), locale="fr")
+    /// 'Fermé · ouvre lun. 09:00'
+    #[text_signature = "(self, time=None, /, locale='en')"]
+    #[args(time = "None", locale = "\"en\"")]
+    fn status_line(&self, time: Option<NaiveDateTimeWrapper>, locale: &str) -> PyResult<String> {
+        let wording = STATUS_WORDINGS
+            .iter()
+            .find(|wording| wording.locale == locale)
+            .ok_or_else(|| PyValueError::new_err(format!("unsupported locale: {:?}", locale)))?;
+
+        let time = get_time(time.map(Into::into), self.clock);
+        let kind = self.evaluate_kind(time);
+
+        let state = match kind {
+            RuleKind::Open => wording.open,
+            RuleKind::Closed => wording.closed,
+            RuleKind::Unknown => wording.unknown,
+        };
+
+        // Changes of comments alone are not worth mentioning.
+        let next = match self
+            .next_state_change(time)
+            .filter(|next| NaiveDateTimeWrapper::from(*next) < NaiveDateTimeWrapper::max_py_value())
+        {
+            Some(next) => next,
+            None => return Ok(state.to_string()),
+        };
+
+        let change = match (kind, self.inner.state(next)) {
+            (_, RuleKind::Open) => wording.opens,
+            (RuleKind::Open, _) => wording.closes,
+            _ => wording.changes,
+        };
+
+        let at = {
+            if next.date() == time.date() {
+                next.format("%H:%M").to_string()
+            } else if next.date() < time.date() + Duration::days(7) {
+                let weekday = wording.weekdays[next.weekday().num_days_from_monday() as usize];
+                format!("{} {}", weekday, next.format("%H:%M"))
+            } else {
+                next.format("%Y-%m-%d %H:%M").to_string()
+            }
+        };
+
+        Ok(format!("{} · {} {}", state, change, at))
+    }
+
+    /// Give an iterator that yields the datetimes of successive changes of
+    /// state after `time`.
+    ///
+    /// Yielded datetimes are strictly increasing: ranges without duration
+    /// and boundaries where only the comments change are skipped, so that
+    /// repeatedly feeding the last result back into `next_change` never
+    /// gets stuck on the same boundary.
+    ///
+    /// Parameters
+    /// ----------
+    /// time : Optional[datetime]
+    ///     Initial time for the iterator, current time will be used if it is
+    ///     not specified.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo 10:00-12:00")
+    /// >>> changes = oh.iter_changes_from(datetime(2020, 11, 2))
+    /// >>> next(changes), next(changes)
+    /// (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0))
+    #[text_signature = "(self, time=None, /)"]
+    fn iter_changes_from(&self, time: Option<NaiveDateTimeWrapper>) -> BoundaryIterator {
+        let time = get_time(time.map(Into::into), self.clock);
+        let ranges = RangeIterator::new(self.inner.clone(), time, None);
+        BoundaryIterator::new(ChangeIterator::new(ranges), time)
+    }
+
+    /// Give an iterator that yields the moments the state becomes open, as
+    /// tuples `(datetime, comments)` holding the comments attached to the
+    /// open state.
+    ///
+    /// Only changes from a state that is not open are reported: the
+    /// iterator is not affected by contiguous open ranges with different
+    /// comments, and an open state at `start` is not reported.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Initial time for the iterator.
+    /// end : Optional[datetime]
+    ///     Maximal time for the iterator, the iterator will continue until
+    ///     year 9999 if it no max is specified.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours('Mo 10:00-12:00 "morning"; Tu 10:00-12:00')
+    /// >>> list(oh.opening_events(datetime(2020, 11, 2), datetime(2020, 11, 4)))
+    /// [(datetime.datetime(2020, 11, 2, 10, 0), ['morning']),
+    ///  (datetime.datetime(2020, 11, 3, 10, 0), [])]
+    #[text_signature = "(self, start, end=None, /, normalize_comments=False)"]
+    #[args(end = "None", normalize_comments = "false")]
+    fn opening_events(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: Option<NaiveDateTimeWrapper>,
+        normalize_comments: bool,
+    ) -> EventIterator {
+        EventIterator::new(RangeIterator::new(
             self.inner.clone(),
-            get_time(start.map(Into::into)),
+            start.into(),
             end.map(Into::into),
-        )
+        ))
+        .with_normalize_comments(normalize_comments)
+    }
+
+    /// Give an iterator that yields the moments the state stops being open,
+    /// as tuples `(datetime, comments)` holding the comments attached to the
+    /// open range that ends.
+    ///
+    /// Only changes from an open state are reported: the iterator is not
+    /// affected by contiguous open ranges with different comments, and a
+    /// closed state at `start` is not reported.
+    ///
+    /// Parameters
+    /// ----------
+    /// start : datetime
+    ///     Initial time for the iterator.
+    /// end : Optional[datetime]
+    ///     Maximal time for the iterator, the iterator will continue until
+    ///     year 9999 if it no max is specified.
+    /// normalize_comments : bool
+    ///     Trim comments and collapse their inner whitespaces.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours('Mo 10:00-12:00 "morning"; Tu 10:00-12:00')
+    /// >>> list(oh.closing_events(datetime(2020, 11, 2), datetime(2020, 11, 4)))
+    /// [(datetime.datetime(2020, 11, 2, 12, 0), ['morning']),
+    ///  (datetime.datetime(2020, 11, 3, 12, 0), [])]
+    #[text_signature = "(self, start, end=None, /, normalize_comments=False)"]
+    #[args(end = "None", normalize_comments = "false")]
+    fn closing_events(
+        &self,
+        start: NaiveDateTimeWrapper,
+        end: Option<NaiveDateTimeWrapper>,
+        normalize_comments: bool,
+    ) -> EventIterator {
+        EventIterator::new(RangeIterator::new(
+            self.inner.clone(),
+            start.into(),
+            end.map(Into::into),
+        ))
+        .with_closing(true)
+        .with_normalize_comments(normalize_comments)
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for OpeningHours {
+    fn __iter__(slf: PyRef<Self>) -> RangeIterator {
+        RangeIterator::new(slf.inner.clone(), get_time(None, slf.clock), None)
+    }
+}
+
+#[pyproto]
+impl PyMappingProtocol for OpeningHours {
+    fn __getitem__(&self, key: &PyAny) -> PyResult<Vec<IntervalTuple>> {
+        let slice: &PySlice = key.downcast().map_err(|_| {
+            PyTypeError::new_err("OpeningHours can only be indexed with a slice of dates")
+        })?;
+
+        if !slice.getattr("step")?.is_none() {
+            return Err(PyValueError::new_err("slice step is not supported"));
+        }
+
+        let start: Option<NaiveDateTimeWrapper> = slice.getattr("start")?.extract()?;
+        let end: Option<NaiveDateTimeWrapper> = slice.getattr("stop")?.extract()?;
+        let end = end.ok_or_else(|| PyValueError::new_err("slice end must be specified"))?;
+
+        Ok(self
+            .iter_window(get_time(start.map(Into::into), self.clock), end.into())
+            .map(|dt_range| interval_tuple(dt_range, false))
+            .collect())
+    }
+}
+
+/// Two objects are equal if their expressions have the same structure once
+/// parsed, regardless of how they are formatted, and they are shifted and
+/// restricted the same way: see `dedupe` to compare the states they give
+/// instead.
+///
+/// Objects are hashable, equal objects have the same hash. Ordering
+/// comparisons and comparisons with other types are not implemented.
+#[pyproto]
+impl PyObjectProtocol for OpeningHours {
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+
+        let other = match other.extract::<PyRef<OpeningHours>>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+
+        let is_equal = Arc::ptr_eq(&self.inner, &other.inner) || *self.inner == *other.inner;
+
+        match op {
+            CompareOp::Eq => is_equal.into_py(py),
+            CompareOp::Ne => (!is_equal).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.inner.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Group expressions that give the same states during a time window and
+/// return the first expression of each group, in input order.
+///
+/// Comments are ignored when comparing expressions.
+///
+/// Parameters
+/// ----------
+/// expressions : List[str]
+///     Opening hours expressions to deduplicate.
+/// start : datetime
+///     Start of the window used for comparison.
+/// end : datetime
+///     End of the window used for comparison, which is excluded.
+///
+/// Raises
+/// ------
+/// SyntaxError
+///     One of the expressions is not in valid opening hours format.
+///
+/// Examples
+/// --------
+/// >>> opening_hours.dedupe(
+/// ...     ["Mo-Fr 10:00-18:00", "Mo,Tu,We,Th,Fr 10:00-18:00", "24/7"],
+/// ...     datetime(2020, 11, 2),
+/// ...     datetime(2020, 11, 9),
+/// ... )
+/// ['Mo-Fr 10:00-18:00', '24/7']
+#[pyfunction]
+#[text_signature = "(expressions, start, end, /)"]
+fn dedupe(
+    expressions: Vec<&str>,
+    start: NaiveDateTimeWrapper,
+    end: NaiveDateTimeWrapper,
+) -> PyResult<Vec<String>> {
+    let mut representatives: Vec<(&str, Vec<(Range<NaiveDateTime>, RuleKind)>)> = Vec::new();
+
+    for oh in expressions {
+        let td = parse(oh, false, DEFAULT_MAX_LENGTH)?;
+        let timeline = OpeningHours::from_domain(Domain::new(td), oh.to_string())
+            .state_timeline(start.into(), end.into());
+
+        if !representatives.iter().any(|(_, known)| *known == timeline) {
+            representatives.push((oh, timeline));
+        }
     }
+
+    Ok(representatives
+        .into_iter()
+        .map(|(oh, _)| oh.to_string())
+        .collect())
+}
+
+/// Get the version of the underlying `opening-hours` crate this module was
+/// built against.
+///
+/// Examples
+/// --------
+/// >>> opening_hours.core_version()
+/// '0.1.9'
+#[pyfunction]
+#[text_signature = "()"]
+fn core_version() -> &'static str {
+    env!("OPENING_HOURS_CORE_VERSION")
+}
+
+/// Render type stubs describing the signatures exposed by this module, in
+/// the format of a `.pyi` file.
+///
+/// Parameters and their default values are read from the signatures of the
+/// objects exposed by the module.
+///
+/// Raises
+/// ------
+/// RuntimeError
+///     The types declared for the stubs don't match the objects exposed by
+///     the module.
+///
+/// Examples
+/// --------
+/// >>> with open("opening_hours.pyi", "w") as f:
+/// ...     f.write(opening_hours.generate_stubs())
+#[pyfunction]
+#[text_signature = "()"]
+fn generate_stubs(py: Python) -> PyResult<String> {
+    stubs::generate(py)
 }
 
 #[pymodule]
-/// TODO: documentation
+/// Parse and evaluate opening hours expressions, as used in OpenStreetMap.
+///
+/// Dates beyond year 9999 can't be represented in Python, they are the bound
+/// of every search and the following policy applies to queries reaching it:
+///
+/// - queries over a window `[start, end)` only inspect this window and
+///   always terminate, an empty window gives an empty result ;
+/// - unbounded iterators, such as `OpeningHours.intervals` without an `end`,
+///   stop at year 9999 and yield an interval ending at `None` if the state
+///   never changes again ;
+/// - unbounded queries returning a single date, such as
+///   `OpeningHours.next_change`, return `None` if the state never changes
+///   again, unless they explicitly allow to raise instead.
 fn opening_hours(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(validate, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(validate_and_normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_evaluable, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_datetime, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_verbose, m)?)?;
+    m.add_function(wrap_pyfunction!(dedupe, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_stubs, m)?)?;
+    m.add_function(wrap_pyfunction!(core_version, m)?)?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     m.add_class::<OpeningHours>()?;
+    m.add_class::<Interval>()?;
+    m.add_class::<Transition>()?;
+    m.add_class::<Summary>()?;
     Ok(())
 }
@@ -0,0 +1,52 @@
+/// Report constructs of an expression that are tolerated by the parser but are
+/// not part of the canonical opening hours syntax.
+pub fn lint(oh: &str) -> Vec<&'static str> {
+    let mut warnings = Vec::new();
+
+    if oh.trim() != oh {
+        warnings.push("expression has leading or trailing whitespace");
+    }
+
+    let mut in_comment = false;
+    let mut prev_char = None;
+    let mut rule_is_empty = true;
+    let mut has_separator = false;
+    let mut has_extra_whitespace = false;
+    let mut has_empty_rule = false;
+
+    for c in oh.chars() {
+        if c == '"' {
+            in_comment = !in_comment;
+        }
+
+        if in_comment {
+            rule_is_empty = false;
+            prev_char = Some(c);
+            continue;
+        }
+
+        match c {
+            ';' => {
+                has_empty_rule |= rule_is_empty;
+                has_separator = true;
+                rule_is_empty = true;
+            }
+            ' ' if prev_char == Some(' ') => has_extra_whitespace = true,
+            ' ' => {}
+            _ if c.is_whitespace() => has_extra_whitespace = true,
+            _ => rule_is_empty = false,
+        }
+
+        prev_char = Some(c);
+    }
+
+    if has_extra_whitespace {
+        warnings.push("expression contains consecutive or non-space whitespace");
+    }
+
+    if has_empty_rule || (has_separator && rule_is_empty) {
+        warnings.push("expression contains an empty rule");
+    }
+
+    warnings
+}
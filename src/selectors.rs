@@ -0,0 +1,396 @@
+use chrono::Datelike;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use opening_hours::day_selector::{DaySelector, WeekDayRange};
+use opening_hours::time_domain::{RuleKind, TimeDomain};
+use opening_hours::time_selector::{Time, TimeSelector};
+
+/// Short names of weekdays, as written in expressions.
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Check if any rule of the time domain depends on public or school holidays.
+pub fn uses_holidays(td: &TimeDomain) -> bool {
+    td.rules.iter().any(|rule| {
+        rule.day_selector
+            .weekday
+            .iter()
+            .any(|weekday| matches!(weekday, WeekDayRange::Holiday { .. }))
+    })
+}
+
+/// Check if any rule of the time domain depends on solar events: dawn,
+/// sunrise, sunset or dusk.
+pub fn uses_solar_events(td: &TimeDomain) -> bool {
+    td.rules.iter().any(|rule| {
+        rule.time_selector.time.iter().any(|span| {
+            matches!(span.range.start, Time::Variable(_))
+                || matches!(span.range.end, Time::Variable(_))
+        })
+    })
+}
+
+/// Check if some rule of the time domain may open during `year` or after it,
+/// rules that don't have a year selector apply to any year.
+pub fn may_open_after(td: &TimeDomain, year: i32) -> bool {
+    td.rules.iter().any(|rule| {
+        let years = &rule.day_selector.year;
+
+        rule.kind == RuleKind::Open
+            && (years.is_empty()
+                || years
+                    .iter()
+                    .any(|range| i32::from(*range.range.end()) >= year))
+    })
+}
+
+/// Describe the year and weekday selectors of a rule as Python objects that
+/// can be serialized to JSON, other selectors are only given in `raw`.
+pub fn day_selector_to_py<'py>(py: Python<'py>, selector: &DaySelector) -> PyResult<&'py PyDict> {
+    let years = PyList::empty(py);
+
+    for year in &selector.year {
+        let py_year = PyDict::new(py);
+        py_year.set_item("start", *year.range.start())?;
+        py_year.set_item("end", *year.range.end())?;
+        years.append(py_year)?;
+    }
+
+    let weekdays = PyList::empty(py);
+
+    for weekday in &selector.weekday {
+        let py_weekday = PyDict::new(py);
+
+        match weekday {
+            WeekDayRange::Fixed { range, offset, nth } => {
+                let start = range.start().num_days_from_monday() as usize;
+                let end = range.end().num_days_from_monday() as usize;
+                let nth: Vec<bool> = nth.iter().copied().collect();
+                py_weekday.set_item("start", WEEKDAYS[start])?;
+                py_weekday.set_item("end", WEEKDAYS[end])?;
+                py_weekday.set_item("nth", nth)?;
+                py_weekday.set_item("offset", *offset)?;
+            }
+            WeekDayRange::Holiday { kind, offset } => {
+                py_weekday.set_item("holiday", format!("{:?}", kind).to_lowercase())?;
+                py_weekday.set_item("offset", *offset)?;
+            }
+        }
+
+        weekdays.append(py_weekday)?;
+    }
+
+    let py_selector = PyDict::new(py);
+    py_selector.set_item("year", years)?;
+    py_selector.set_item("weekday", weekdays)?;
+    py_selector.set_item("raw", format!("{:?}", selector))?;
+    Ok(py_selector)
+}
+
+/// Describe a time written in a time selector, either as `HH:MM` or as a
+/// solar event with an offset in minutes.
+fn time_to_py(py: Python, time: &Time) -> PyResult<PyObject> {
+    Ok(match time {
+        Time::Fixed(time) => format!("{:02}:{:02}", time.hour(), time.minute()).into_py(py),
+        Time::Variable(time) => {
+            let py_time = PyDict::new(py);
+            py_time.set_item("event", format!("{:?}", time.event).to_lowercase())?;
+            py_time.set_item("offset", time.offset)?;
+            py_time.into_py(py)
+        }
+    })
+}
+
+/// Describe the time spans of a rule as Python objects that can be
+/// serialized to JSON.
+pub fn time_selector_to_py<'py>(py: Python<'py>, selector: &TimeSelector) -> PyResult<&'py PyList> {
+    let spans = PyList::empty(py);
+
+    for span in &selector.time {
+        let py_span = PyDict::new(py);
+        py_span.set_item("start", time_to_py(py, &span.range.start)?)?;
+        py_span.set_item("end", time_to_py(py, &span.range.end)?)?;
+        spans.append(py_span)?;
+    }
+
+    Ok(spans)
+}
+
+/// Error for a JSON document that doesn't describe rules as exported by
+/// `to_ast_json`.
+fn invalid_ast(detail: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(format!("invalid AST: {}", detail))
+}
+
+/// Get a field of an object described in JSON.
+fn field<'py, T: FromPyObject<'py>>(object: &'py PyAny, key: &str) -> PyResult<T> {
+    let object: &PyDict = object
+        .downcast()
+        .map_err(|_| invalid_ast(format!("expected an object holding `{}`", key)))?;
+
+    object
+        .get_item(key)
+        .ok_or_else(|| invalid_ast(format!("missing field `{}`", key)))?
+        .extract()
+        .map_err(|_| invalid_ast(format!("unexpected type for field `{}`", key)))
+}
+
+/// Write an offset in days, as it follows weekdays and holidays.
+fn day_offset_from_py(offset: i64) -> String {
+    match offset {
+        0 => String::new(),
+        1 | -1 => format!(" {:+} day", offset),
+        _ => format!(" {:+} days", offset),
+    }
+}
+
+/// Write a weekday range or a holiday described by `day_selector_to_py`.
+fn weekday_from_py(weekday: &PyAny) -> PyResult<(bool, String)> {
+    let offset = day_offset_from_py(field(weekday, "offset")?);
+
+    if let Ok(holiday) = field::<String>(weekday, "holiday") {
+        let holiday = match holiday.as_str() {
+            "public" => "PH",
+            "school" => "SH",
+            _ => return Err(invalid_ast(format!("unknown holiday `{}`", holiday))),
+        };
+
+        return Ok((true, holiday.to_string() + &offset));
+    }
+
+    let start: String = field(weekday, "start")?;
+    let end: String = field(weekday, "end")?;
+    let nth: Vec<bool> = field(weekday, "nth")?;
+
+    for day in &[&start, &end] {
+        if !WEEKDAYS.contains(&day.as_str()) {
+            return Err(invalid_ast(format!("unknown weekday `{}`", day)));
+        }
+    }
+
+    let mut text = if start == end {
+        start
+    } else {
+        format!("{}-{}", start, end)
+    };
+
+    if nth.iter().any(|selected| !selected) {
+        let positions: Vec<_> = nth
+            .iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(i, _)| (i + 1).to_string())
+            .collect();
+
+        text = format!("{}[{}]", text, positions.join(","));
+    }
+
+    Ok((false, text + &offset))
+}
+
+/// Write the year and weekday selectors described by `day_selector_to_py`.
+fn day_selector_from_py(selector: &PyAny) -> PyResult<Vec<String>> {
+    let mut selectors = Vec::new();
+    let mut years = Vec::new();
+
+    for year in field::<Vec<&PyAny>>(selector, "year")? {
+        let start: u16 = field(year, "start")?;
+        let end: u16 = field(year, "end")?;
+
+        years.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        });
+    }
+
+    if !years.is_empty() {
+        selectors.push(years.join(","));
+    }
+
+    // Weekdays and holidays are written as two groups, in the order they
+    // appear in the rule.
+    let mut weekdays = Vec::new();
+    let mut holidays = Vec::new();
+    let mut holidays_first = false;
+
+    for weekday in field::<Vec<&PyAny>>(selector, "weekday")? {
+        let (is_holiday, text) = weekday_from_py(weekday)?;
+        holidays_first |= is_holiday && weekdays.is_empty();
+
+        if is_holiday {
+            holidays.push(text);
+        } else {
+            weekdays.push(text);
+        }
+    }
+
+    let mut groups = vec![weekdays.join(","), holidays.join(",")];
+
+    if holidays_first {
+        groups.reverse();
+    }
+
+    let groups: Vec<_> = groups
+        .into_iter()
+        .filter(|group| !group.is_empty())
+        .collect();
+
+    if !groups.is_empty() {
+        selectors.push(groups.join(","));
+    }
+
+    Ok(selectors)
+}
+
+/// Write a bound of a time span described by `time_to_py`.
+fn time_from_py(time: &PyAny) -> PyResult<String> {
+    if let Ok(time) = time.extract::<String>() {
+        let bytes = time.as_bytes();
+        let is_valid = bytes.len() == 5
+            && bytes[2] == b':'
+            && bytes
+                .iter()
+                .enumerate()
+                .all(|(i, c)| i == 2 || c.is_ascii_digit());
+
+        if !is_valid {
+            return Err(invalid_ast(format!("invalid time `{}`", time)));
+        }
+
+        return Ok(time);
+    }
+
+    let event: String = field(time, "event")?;
+    let offset: i64 = field(time, "offset")?;
+
+    if !["dawn", "sunrise", "sunset", "dusk"].contains(&event.as_str()) {
+        return Err(invalid_ast(format!("unknown event `{}`", event)));
+    }
+
+    if offset == 0 {
+        return Ok(event);
+    }
+
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    Ok(format!(
+        "({}{}{:02}:{:02})",
+        event,
+        sign,
+        offset / 60,
+        offset % 60
+    ))
+}
+
+/// Write a rule described by `to_ast_json`, without its operator.
+fn rule_from_py(rule: &PyAny) -> PyResult<String> {
+    let mut parts = day_selector_from_py(field(rule, "day_selector")?)?;
+    let mut spans = Vec::new();
+
+    for span in field::<Vec<&PyAny>>(rule, "time_selector")? {
+        let start = time_from_py(field(span, "start")?)?;
+        let end = time_from_py(field(span, "end")?)?;
+        spans.push(format!("{}-{}", start, end));
+    }
+
+    if !spans.is_empty() {
+        parts.push(spans.join(","));
+    }
+
+    if parts.is_empty() {
+        parts.push("24/7".to_string());
+    }
+
+    match field::<String>(rule, "kind")?.as_str() {
+        "open" => {}
+        "closed" => parts.push("off".to_string()),
+        "unknown" => parts.push("unknown".to_string()),
+        kind => return Err(invalid_ast(format!("unknown kind `{}`", kind))),
+    }
+
+    match field::<Vec<String>>(rule, "comments")?.as_slice() {
+        [] => {}
+        [comment] if !comment.contains('"') => parts.push(format!("\"{}\"", comment)),
+        [_] => return Err(invalid_ast("comments can't contain quotes")),
+        _ => return Err(invalid_ast("a rule can only hold one comment")),
+    }
+
+    Ok(parts.join(" "))
+}
+
+/// Write the expression of the rules described by `to_ast_json`, given as
+/// decoded JSON.
+pub fn expression_from_py(ast: &PyAny) -> PyResult<String> {
+    let rules: Vec<&PyAny> = field(ast, "rules")?;
+    let mut expression = String::new();
+
+    for (i, rule) in rules.into_iter().enumerate() {
+        if i > 0 {
+            expression += match field::<String>(rule, "operator")?.as_str() {
+                "normal" => "; ",
+                "additional" => ", ",
+                "fallback" => " || ",
+                operator => return Err(invalid_ast(format!("unknown operator `{}`", operator))),
+            };
+        }
+
+        expression += &rule_from_py(rule)?;
+    }
+
+    if expression.is_empty() {
+        return Err(invalid_ast("expected at least one rule"));
+    }
+
+    Ok(expression)
+}
+
+/// Check that the selectors of the parsed rules match the `raw` debug
+/// representation given with each rule: selectors that are not described
+/// by `day_selector_to_py` would otherwise be silently dropped.
+pub fn check_raw_day_selectors(ast: &PyAny, td: &TimeDomain) -> PyResult<()> {
+    let rules: Vec<&PyAny> = field(ast, "rules")?;
+
+    if rules.len() != td.rules.len() {
+        return Err(invalid_ast("the rules can't be written as an expression"));
+    }
+
+    for (i, (rule, parsed)) in rules.into_iter().zip(&td.rules).enumerate() {
+        let selector = field(rule, "day_selector")?;
+
+        if let Ok(raw) = field::<String>(selector, "raw") {
+            if raw != format!("{:?}", parsed.day_selector) {
+                return Err(invalid_ast(format!(
+                    "day selectors of rule {} are not supported",
+                    i
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the range of years covered by the time domain, or `None` if some rule
+/// doesn't have a year selector and thus applies to any year.
+pub fn year_span(td: &TimeDomain) -> Option<(i32, i32)> {
+    td.rules
+        .iter()
+        .try_fold(None, |span: Option<(i32, i32)>, rule| {
+            let years = &rule.day_selector.year;
+            let first = years
+                .iter()
+                .map(|year| i32::from(*year.range.start()))
+                .min()?;
+            let last = years
+                .iter()
+                .map(|year| i32::from(*year.range.end()))
+                .max()?;
+
+            Some(Some(match span {
+                Some((min, max)) => (min.min(first), max.max(last)),
+                None => (first, last),
+            }))
+        })?
+}
@@ -0,0 +1,801 @@
+//! Type stubs for the extension module.
+//!
+//! Python tooling can't introspect the signatures of a compiled module, so
+//! they are rendered into a `.pyi` file by `generate`. Parameter names and
+//! default values are read from the text signature of the registered objects
+//! and this file only provides their types: generation fails if an entry
+//! doesn't match the objects exposed by the module, or if a public method
+//! has no entry.
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::type_object::PyTypeObject;
+use pyo3::types::PyType;
+
+use crate::types::{
+    BatchIterator, BoundaryIterator, ChangeIterator, EventIterator, Interval, RangeIterator,
+    Summary, Transition, WeekIterator,
+};
+use crate::OpeningHours;
+
+const HEADER: &str = "\
+from datetime import date, datetime, time, timedelta
+from typing import Any, Callable, Dict, Iterator, List, Literal, Optional, Tuple, Union
+
+__version__: str
+
+State = Literal[\"open\", \"closed\", \"unknown\"]
+DateTimeLike = Union[datetime, date, Tuple[date, time], str]
+IntervalTuple = Tuple[datetime, Optional[datetime], State, List[str]]
+
+# Start of `OpeningHours.intervals`: current time is used if it is omitted,
+# while an explicit `None` stands for `datetime.min`. Every other query uses
+# current time for `None`.
+IntervalsStart = Optional[DateTimeLike]
+";
+
+enum Member {
+    /// Plain attribute, such as a field exposed with `#[pyo3(get)]`.
+    Attribute {
+        name: &'static str,
+        ty: &'static str,
+    },
+    /// Computed attribute, exposed with `#[getter]`.
+    Property {
+        name: &'static str,
+        ty: &'static str,
+    },
+    /// Function or method, with the type of each of its parameters.
+    Method {
+        name: &'static str,
+        params: &'static [(&'static str, &'static str)],
+        returns: &'static str,
+    },
+}
+
+const fn attribute(name: &'static str, ty: &'static str) -> Member {
+    Member::Attribute { name, ty }
+}
+
+const fn property(name: &'static str, ty: &'static str) -> Member {
+    Member::Property { name, ty }
+}
+
+const fn method(
+    name: &'static str,
+    params: &'static [(&'static str, &'static str)],
+    returns: &'static str,
+) -> Member {
+    Member::Method {
+        name,
+        params,
+        returns,
+    }
+}
+
+impl Member {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Attribute { name, .. }
+            | Self::Property { name, .. }
+            | Self::Method { name, .. } => name,
+        }
+    }
+}
+
+struct Class {
+    name: &'static str,
+    ty: for<'py> fn(Python<'py>) -> &'py PyType,
+    members: &'static [Member],
+}
+
+fn type_of<T: PyTypeObject>(py: Python) -> &PyType {
+    py.get_type::<T>()
+}
+
+const FUNCTIONS: &[Member] = &[
+    method(
+        "validate",
+        &[
+            ("oh", "Union[str, bytes]"),
+            ("strict", "bool"),
+            ("max_length", "int"),
+            ("tolerant", "bool"),
+        ],
+        "bool",
+    ),
+    method(
+        "validate_evaluable",
+        &[
+            ("oh", "Union[str, bytes]"),
+            ("has_coords", "bool"),
+            ("has_holidays", "bool"),
+        ],
+        "bool",
+    ),
+    method(
+        "validate_and_normalize",
+        &[("oh", "Union[str, bytes]")],
+        "str",
+    ),
+    method("parse_datetime", &[("s", "str")], "datetime"),
+    method(
+        "parse_verbose",
+        &[("oh", "Union[str, bytes]")],
+        "Tuple[OpeningHours, List[str]]",
+    ),
+    method(
+        "dedupe",
+        &[
+            ("expressions", "List[str]"),
+            ("start", "DateTimeLike"),
+            ("end", "DateTimeLike"),
+        ],
+        "List[str]",
+    ),
+    method("generate_stubs", &[], "str"),
+    method("core_version", &[], "str"),
+];
+
+const CLASSES: &[Class] = &[
+    Class {
+        name: "OpeningHours",
+        ty: type_of::<OpeningHours>,
+        members: &[
+            attribute("corrections", "List[str]"),
+            method(
+                "__init__",
+                &[
+                    ("oh", "Union[str, bytes]"),
+                    ("strict_holidays", "bool"),
+                    ("strict", "bool"),
+                    ("max_length", "int"),
+                    ("tolerant", "bool"),
+                    ("clock", "Literal[\"local\", \"utc\"]"),
+                ],
+                "None",
+            ),
+            method(
+                "state",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("unknown_outside_years", "bool"),
+                ],
+                "State",
+            ),
+            method(
+                "is_open",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("grace", "Optional[timedelta]"),
+                    ("min_remaining", "Optional[timedelta]"),
+                ],
+                "bool",
+            ),
+            method("is_closed", &[("time", "Optional[DateTimeLike]")], "bool"),
+            method("is_unknown", &[("time", "Optional[DateTimeLike]")], "bool"),
+            method(
+                "next_change",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("no_change", "Literal[\"none\", \"max\", \"raise\"]"),
+                    ("collapse_unknown", "bool"),
+                ],
+                "Optional[datetime]",
+            ),
+            method(
+                "next_change_detail",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("source_comments", "bool"),
+                    ("structured", "bool"),
+                    ("normalize_comments", "bool"),
+                ],
+                "Union[None, Tuple[datetime, State, List[str]], Transition]",
+            ),
+            method(
+                "intervals",
+                &[
+                    ("start", "Optional[DateTimeLike]"),
+                    ("end", "Optional[DateTimeLike]"),
+                    ("translate", "Optional[Callable[[str], str]]"),
+                    ("structured", "bool"),
+                    ("comment_contains", "Optional[str]"),
+                    ("clip_start_to_now", "bool"),
+                    ("normalize_comments", "bool"),
+                    ("inclusive_end", "bool"),
+                    ("daily_window", "Optional[Tuple[time, time]]"),
+                ],
+                "RangeIterator",
+            ),
+            method(
+                "changes",
+                &[
+                    ("start", "Optional[DateTimeLike]"),
+                    ("end", "Optional[DateTimeLike]"),
+                    ("structured", "bool"),
+                    ("normalize_comments", "bool"),
+                    ("collapse_unknown", "bool"),
+                ],
+                "ChangeIterator",
+            ),
+            method(
+                "open_minutes_by_weekday",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "List[int]",
+            ),
+            method(
+                "total_changes",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "int",
+            ),
+            method("to_ast_json", &[], "str"),
+            method("from_ast_json", &[("ast", "str")], "OpeningHours"),
+            method(
+                "diff",
+                &[
+                    ("other", "OpeningHours"),
+                    ("start", "DateTimeLike"),
+                    ("end", "DateTimeLike"),
+                ],
+                "List[Tuple[Tuple[datetime, datetime], State, State]]",
+            ),
+            method("shift", &[("delta", "timedelta")], "OpeningHours"),
+            method(
+                "restrict",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "OpeningHours",
+            ),
+            method(
+                "state_breakdown",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "Dict[State, Tuple[int, timedelta]]",
+            ),
+            method(
+                "is_open_at",
+                &[("weekday", "int"), ("time", "time")],
+                "bool",
+            ),
+            method(
+                "comments_active_at",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("normalize_comments", "bool"),
+                ],
+                "List[str]",
+            ),
+            method(
+                "next_changes",
+                &[("times", "List[DateTimeLike]")],
+                "List[Optional[datetime]]",
+            ),
+            method(
+                "is_open_during",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "bool",
+            ),
+            method(
+                "is_open_throughout",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "bool",
+            ),
+            method("__copy__", &[], "OpeningHours"),
+            method(
+                "__reduce__",
+                &[],
+                "Tuple[type, Tuple[str, bool, bool, int, bool, str], Dict[str, Any]]",
+            ),
+            method("__setstate__", &[("state", "Dict[str, Any]")], "None"),
+            method("__deepcopy__", &[("memo", "Any")], "OpeningHours"),
+            method("open_ranges_as_tuples", &[], "List[Tuple[int, time, time]]"),
+            method("state_now", &[], "State"),
+            method("is_open_now", &[], "bool"),
+            method("is_closed_now", &[], "bool"),
+            method("is_unknown_now", &[], "bool"),
+            method(
+                "boundaries",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "List[datetime]",
+            ),
+            method("uses_solar_events", &[], "bool"),
+            method("requires_holidays", &[], "bool"),
+            method(
+                "change_times",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "List[datetime]",
+            ),
+            method("earliest_open", &[("date", "date")], "Optional[time]"),
+            method("latest_close", &[("date", "date")], "Optional[time]"),
+            method(
+                "month_status",
+                &[("year", "int"), ("month", "int")],
+                "List[Literal[\"open\", \"closed\", \"partial\"]]",
+            ),
+            method("state_code", &[("time", "Optional[DateTimeLike]")], "int"),
+            method(
+                "states_codes",
+                &[("times", "List[DateTimeLike]")],
+                "List[int]",
+            ),
+            method(
+                "summarize",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "DateTimeLike"),
+                    ("normalize_comments", "bool"),
+                ],
+                "Summary",
+            ),
+            method(
+                "closest_change",
+                &[("time", "Optional[DateTimeLike]")],
+                "Optional[Tuple[datetime, Literal[\"previous\", \"next\"]]]",
+            ),
+            method(
+                "current_range",
+                &[("time", "Optional[DateTimeLike]")],
+                "Optional[Tuple[Optional[datetime], Optional[datetime]]]",
+            ),
+            method(
+                "last_intervals",
+                &[
+                    ("n", "int"),
+                    ("before", "Optional[DateTimeLike]"),
+                    ("normalize_comments", "bool"),
+                ],
+                "List[IntervalTuple]",
+            ),
+            method(
+                "next_open_day",
+                &[("from_date", "Optional[date]"), ("horizon", "int")],
+                "Optional[date]",
+            ),
+            method(
+                "open_window_count",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "int",
+            ),
+            method(
+                "transition_counts",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "Dict[Tuple[State, State], int]",
+            ),
+            method("are_open", &[("times", "List[DateTimeLike]")], "List[bool]"),
+            method(
+                "classify",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("rules", "Dict[str, str]"),
+                ],
+                "Optional[str]",
+            ),
+            method(
+                "opening_intervals",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "DateTimeLike"),
+                    ("merge_gaps", "Optional[timedelta]"),
+                    ("with_duration", "bool"),
+                ],
+                "Union[List[Tuple[datetime, datetime]], List[Tuple[datetime, datetime, timedelta]]]",
+            ),
+            method(
+                "closed_reason",
+                &[("time", "Optional[DateTimeLike]")],
+                "Optional[str]",
+            ),
+            method(
+                "intervals_batched",
+                &[
+                    ("start", "Optional[DateTimeLike]"),
+                    ("end", "Optional[DateTimeLike]"),
+                    ("batch_size", "int"),
+                ],
+                "BatchIterator",
+            ),
+            method(
+                "next_open",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("weekday", "Optional[int]"),
+                    ("horizon", "int"),
+                ],
+                "Optional[datetime]",
+            ),
+            method(
+                "next_closed",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("weekday", "Optional[int]"),
+                    ("horizon", "int"),
+                ],
+                "Optional[datetime]",
+            ),
+            method(
+                "open_duration",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "DateTimeLike"),
+                    ("daily_window", "Optional[Tuple[time, time]]"),
+                ],
+                "timedelta",
+            ),
+            method(
+                "closed_duration",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "timedelta",
+            ),
+            method(
+                "open_on",
+                &[("date", "date")],
+                "List[Tuple[datetime, datetime]]",
+            ),
+            method(
+                "next_comments",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("normalize_comments", "bool"),
+                ],
+                "Optional[List[str]]",
+            ),
+            method(
+                "open_fraction_by_day",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "List[Tuple[date, float]]",
+            ),
+            method("to_cron", &[], "Optional[List[str]]"),
+            method(
+                "iter_weeks",
+                &[("start", "DateTimeLike"), ("end", "DateTimeLike")],
+                "WeekIterator",
+            ),
+            method(
+                "nearest_open_range",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("direction", "Literal[\"nearest\", \"next\", \"previous\"]"),
+                    ("horizon", "int"),
+                ],
+                "Optional[Tuple[datetime, datetime]]",
+            ),
+            method("pretty", &[("week_of", "Optional[date]")], "str"),
+            method(
+                "intervals_by_day",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "DateTimeLike"),
+                    ("normalize_comments", "bool"),
+                ],
+                "Dict[date, List[IntervalTuple]]",
+            ),
+            method(
+                "status_line",
+                &[
+                    ("time", "Optional[DateTimeLike]"),
+                    ("locale", "Literal[\"en\", \"fr\"]"),
+                ],
+                "str",
+            ),
+            method(
+                "iter_changes_from",
+                &[("time", "Optional[DateTimeLike]")],
+                "BoundaryIterator",
+            ),
+            method(
+                "opening_events",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "Optional[DateTimeLike]"),
+                    ("normalize_comments", "bool"),
+                ],
+                "EventIterator",
+            ),
+            method(
+                "closing_events",
+                &[
+                    ("start", "DateTimeLike"),
+                    ("end", "Optional[DateTimeLike]"),
+                    ("normalize_comments", "bool"),
+                ],
+                "EventIterator",
+            ),
+            method("overlay", &[("other", "OpeningHours")], "OpeningHours"),
+            method("__iter__", &[], "RangeIterator"),
+            method("__getitem__", &[("key", "slice")], "List[IntervalTuple]"),
+            method("__eq__", &[("value", "object")], "bool"),
+            method("__hash__", &[], "int"),
+        ],
+    },
+    Class {
+        name: "RangeIterator",
+        ty: type_of::<RangeIterator>,
+        members: &[
+            method("resume_token", &[], "str"),
+            method(
+                "take_until",
+                &[("predicate", "Callable[[Any], bool]")],
+                "List[Any]",
+            ),
+            method("__iter__", &[], "RangeIterator"),
+            method("__next__", &[], "Union[IntervalTuple, Interval]"),
+        ],
+    },
+    Class {
+        name: "ChangeIterator",
+        ty: type_of::<ChangeIterator>,
+        members: &[
+            method("__iter__", &[], "ChangeIterator"),
+            method(
+                "__next__",
+                &[],
+                "Union[Tuple[datetime, State, State], Transition]",
+            ),
+        ],
+    },
+    Class {
+        name: "BatchIterator",
+        ty: type_of::<BatchIterator>,
+        members: &[
+            method("__iter__", &[], "BatchIterator"),
+            method("__next__", &[], "List[IntervalTuple]"),
+        ],
+    },
+    Class {
+        name: "WeekIterator",
+        ty: type_of::<WeekIterator>,
+        members: &[
+            method("__iter__", &[], "WeekIterator"),
+            method(
+                "__next__",
+                &[],
+                "Tuple[date, Dict[int, List[Tuple[time, time]]]]",
+            ),
+        ],
+    },
+    Class {
+        name: "BoundaryIterator",
+        ty: type_of::<BoundaryIterator>,
+        members: &[
+            method("__iter__", &[], "BoundaryIterator"),
+            method("__next__", &[], "datetime"),
+        ],
+    },
+    Class {
+        name: "EventIterator",
+        ty: type_of::<EventIterator>,
+        members: &[
+            method("__iter__", &[], "EventIterator"),
+            method("__next__", &[], "Tuple[datetime, List[str]]"),
+        ],
+    },
+    Class {
+        name: "Interval",
+        ty: type_of::<Interval>,
+        members: &[
+            attribute("start", "datetime"),
+            attribute("end", "Optional[datetime]"),
+            attribute("state", "State"),
+            attribute("comments", "List[str]"),
+            property("duration", "Optional[timedelta]"),
+        ],
+    },
+    Class {
+        name: "Transition",
+        ty: type_of::<Transition>,
+        members: &[
+            attribute("at", "datetime"),
+            attribute("from_state", "State"),
+            attribute("to_state", "State"),
+            attribute("comments", "List[str]"),
+        ],
+    },
+    Class {
+        name: "Summary",
+        ty: type_of::<Summary>,
+        members: &[
+            attribute(
+                "state",
+                "Literal[\"open\", \"closed\", \"unknown\", \"mixed\"]",
+            ),
+            attribute("comments", "List[str]"),
+        ],
+    },
+];
+
+/// Read the text signature of a Python object.
+fn text_signature(obj: &PyAny) -> PyResult<Option<String>> {
+    if !obj.hasattr("__text_signature__")? {
+        return Ok(None);
+    }
+
+    obj.getattr("__text_signature__")?.extract()
+}
+
+/// Split the parameters of a text signature such as `(self, x=None, /)`,
+/// commas nested in brackets or quotes don't separate parameters.
+fn split_params(signature: &str) -> Vec<&str> {
+    let inner = signature
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')');
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(' | '{') => depth += 1,
+            (None, ']' | ')' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                params.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if !inner[start..].trim().is_empty() {
+        params.push(inner[start..].trim());
+    }
+
+    params
+}
+
+/// Render the definition of a function from its text signature and the
+/// types of its parameters.
+fn render_method(
+    owner: &str,
+    name: &str,
+    signature: &str,
+    types: &[(&str, &str)],
+    returns: &str,
+) -> PyResult<String> {
+    let mismatch = |detail: String| {
+        PyRuntimeError::new_err(format!(
+            "stub of {}{} doesn't match its signature {}: {}",
+            owner, name, signature, detail
+        ))
+    };
+
+    let mut rendered = Vec::new();
+    let mut decorator = "";
+    let mut typed = 0;
+
+    for param in split_params(signature) {
+        let (param_name, default) = match param.find('=') {
+            Some(sep) => (param[..sep].trim(), Some(param[sep + 1..].trim())),
+            None => (param, None),
+        };
+
+        if let "cls" | "$cls" = param_name {
+            decorator = "@classmethod\n    ";
+        }
+
+        if let "self" | "$self" | "cls" | "$cls" | "/" | "*" = param_name {
+            rendered.push(param_name.trim_start_matches('$').to_string());
+            continue;
+        }
+
+        let ty = types
+            .iter()
+            .find(|(typed_name, _)| *typed_name == param_name)
+            .map(|(_, ty)| ty)
+            .ok_or_else(|| mismatch(format!("no type for parameter {:?}", param_name)))?;
+
+        typed += 1;
+
+        rendered.push(match default {
+            Some(default) => format!("{}: {} = {}", param_name, ty, default),
+            None => format!("{}: {}", param_name, ty),
+        });
+    }
+
+    if typed != types.len() {
+        return Err(mismatch("some typed parameters don't exist".to_string()));
+    }
+
+    Ok(format!(
+        "{}def {}({}) -> {}: ...",
+        decorator,
+        name,
+        rendered.join(", "),
+        returns
+    ))
+}
+
+/// Render a member of a class or module `obj`, `owner` is the prefix used to
+/// refer to this object in error messages.
+fn render_member(obj: &PyAny, owner: &str, member: &Member) -> PyResult<String> {
+    if !obj.hasattr(member.name())? {
+        return Err(PyRuntimeError::new_err(format!(
+            "stub of {}{} doesn't match any registered object",
+            owner,
+            member.name()
+        )));
+    }
+
+    Ok(match member {
+        Member::Attribute { name, ty } => format!("{}: {}", name, ty),
+        Member::Property { name, ty } => {
+            format!("@property\n    def {}(self) -> {}: ...", name, ty)
+        }
+        Member::Method {
+            name,
+            params,
+            returns,
+        } => {
+            // The signature of the constructor is attached to the class.
+            let signature = {
+                if *name == "__init__" {
+                    text_signature(obj)?.map(|sig| format!("(self, {}", &sig[1..]))
+                } else {
+                    text_signature(obj.getattr(*name)?)?
+                }
+            };
+
+            let signature = signature.ok_or_else(|| {
+                PyRuntimeError::new_err(format!("{}{} has no text signature", owner, name))
+            })?;
+
+            render_method(owner, name, &signature, params, returns)?
+        }
+    })
+}
+
+/// Check that all public methods of `obj` are described in `members`.
+fn check_exhaustive(obj: &PyAny, owner: &str, members: &[Member]) -> PyResult<()> {
+    for name in obj.dir() {
+        let name: &str = name.extract()?;
+
+        if name.starts_with('_') || members.iter().any(|member| member.name() == name) {
+            continue;
+        }
+
+        let value = obj.getattr(name)?;
+
+        if !value.is_instance::<PyType>()? && text_signature(value)?.is_some() {
+            return Err(PyRuntimeError::new_err(format!(
+                "{}{} has no stub",
+                owner, name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Render the content of the `.pyi` file describing the module.
+pub fn generate(py: Python) -> PyResult<String> {
+    let module = py.import("opening_hours")?;
+    let mut output = String::from(HEADER);
+
+    check_exhaustive(module, "", FUNCTIONS)?;
+
+    for function in FUNCTIONS {
+        output.push('\n');
+        output.push_str(&render_member(module, "", function)?);
+        output.push('\n');
+    }
+
+    for class in CLASSES {
+        let ty = (class.ty)(py);
+        let owner = format!("{}.", class.name);
+        check_exhaustive(ty, &owner, class.members)?;
+
+        output.push_str("\nclass ");
+        output.push_str(class.name);
+        output.push_str(":\n");
+
+        for member in class.members {
+            output.push_str("    ");
+            output.push_str(&render_member(ty, &owner, member)?);
+            output.push('\n');
+        }
+    }
+
+    Ok(output)
+}
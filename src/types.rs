@@ -1,20 +1,29 @@
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::ops::Range;
 use std::sync::Arc;
 
 use chrono::prelude::*;
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime};
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
-use pyo3::types::{PyDateAccess, PyDateTime, PyTimeAccess};
-use pyo3::PyIterProtocol;
+use pyo3::types::{
+    PyBytes, PyDate, PyDateAccess, PyDateTime, PyDelta, PyDeltaAccess, PyTime, PyTimeAccess,
+};
+use pyo3::{PyIterProtocol, PyObjectProtocol};
 
 use opening_hours::time_domain;
 use time_domain::{DateTimeRange, RuleKind};
 
+use crate::domain::Domain;
+use crate::errors::{DateTimeParseError, ExpressionDecodeError};
+
 // ---
 // --- State
 // ---
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum State {
     Open,
     Closed,
@@ -31,13 +40,54 @@ impl From<RuleKind> for State {
     }
 }
 
+impl State {
+    /// Compact representation of the state, for storage.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Closed => 0,
+            Self::Open => 1,
+            Self::Unknown => 2,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Closed => "closed",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
 impl<'p> IntoPy<Py<PyAny>> for State {
     fn into_py(self, py: Python<'_>) -> Py<PyAny> {
-        match self {
-            Self::Open => "open".into_py(py),
-            Self::Closed => "closed".into_py(py),
-            Self::Unknown => "unknown".into_py(py),
+        self.as_str().into_py(py)
+    }
+}
+
+// ---
+// --- Expression
+// ---
+
+/// An opening hours expression given either as a string or as UTF-8 encoded
+/// bytes.
+pub struct Expression(String);
+
+impl Expression {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'source> FromPyObject<'source> for Expression {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(bytes) = ob.downcast::<PyBytes>() {
+            return std::str::from_utf8(bytes.as_bytes())
+                .map(|oh| Self(oh.to_string()))
+                .map_err(|err| ExpressionDecodeError(err).into());
         }
+
+        Ok(Self(ob.extract()?))
     }
 }
 
@@ -49,6 +99,10 @@ impl<'p> IntoPy<Py<PyAny>> for State {
 pub struct NaiveDateTimeWrapper(NaiveDateTime);
 
 impl NaiveDateTimeWrapper {
+    pub fn min_py_value() -> NaiveDateTimeWrapper {
+        NaiveDate::from_ymd(1, 1, 1).and_hms(0, 0, 0).into()
+    }
+
     pub fn max_py_value() -> NaiveDateTimeWrapper {
         NaiveDateTime::new(
             NaiveDate::from_ymd(9999, 12, 31),
@@ -56,6 +110,34 @@ impl NaiveDateTimeWrapper {
         )
         .into()
     }
+
+    /// Parse a datetime written in one of the common formats, such as ISO
+    /// 8601 or `YYYY-MM-DD HH:MM`. Timezone offsets are ignored and the local
+    /// time they specify is kept.
+    pub fn parse(input: &str) -> Option<Self> {
+        const FORMATS: &[&str] = &[
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M",
+        ];
+
+        let input = input.trim();
+
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+            return Some(datetime.naive_local().into());
+        }
+
+        FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                    .ok()
+                    .map(|date| date.and_hms(0, 0, 0))
+            })
+            .map(Into::into)
+    }
 }
 
 impl Into<NaiveDateTime> for NaiveDateTimeWrapper {
@@ -72,22 +154,49 @@ impl From<NaiveDateTime> for NaiveDateTimeWrapper {
 
 impl<'source> FromPyObject<'source> for NaiveDateTimeWrapper {
     fn extract(ob: &'source PyAny) -> PyResult<Self> {
-        let py_datetime: &PyDateTime = ob.downcast()?;
-        Ok({
-            NaiveDateTime::new(
-                NaiveDate::from_ymd(
-                    py_datetime.get_year(),
-                    py_datetime.get_month().into(),
-                    py_datetime.get_day().into(),
-                ),
-                NaiveTime::from_hms(
-                    py_datetime.get_hour().into(),
-                    py_datetime.get_minute().into(),
-                    py_datetime.get_second().into(),
-                ),
-            )
-            .into()
-        })
+        if let Ok(py_datetime) = ob.downcast::<PyDateTime>() {
+            return Ok({
+                NaiveDateTime::new(
+                    NaiveDate::from_ymd(
+                        py_datetime.get_year(),
+                        py_datetime.get_month().into(),
+                        py_datetime.get_day().into(),
+                    ),
+                    NaiveTime::from_hms(
+                        py_datetime.get_hour().into(),
+                        py_datetime.get_minute().into(),
+                        py_datetime.get_second().into(),
+                    ),
+                )
+                .into()
+            });
+        }
+
+        // Pairs of a date and a time are combined.
+        if let Ok((date, time)) = ob.extract::<(NaiveDateWrapper, NaiveTimeWrapper)>() {
+            return Ok(NaiveDateTime::new(date.into(), time.into()).into());
+        }
+
+        // Strings are parsed from the common formats, which allows to pass
+        // back tokens produced by `RangeIterator.resume_token`.
+        if let Ok(input) = ob.extract::<&str>() {
+            return Self::parse(input).ok_or_else(|| DateTimeParseError(input.to_string()).into());
+        }
+
+        // Dates without time component are interpreted as midnight.
+        let py_date: &PyDate = ob.downcast().map_err(|_| {
+            PyTypeError::new_err(format!(
+                "expected a datetime, a date, a (date, time) pair or a string, got {}",
+                ob.get_type().name()
+            ))
+        })?;
+        let date = NaiveDate::from_ymd(
+            py_date.get_year(),
+            py_date.get_month().into(),
+            py_date.get_day().into(),
+        );
+
+        Ok(date.and_hms(0, 0, 0).into())
     }
 }
 
@@ -123,29 +232,309 @@ impl<'p> IntoPy<Py<PyAny>> for NaiveDateTimeWrapper {
     }
 }
 
+/// Start of an iteration over time, which can be explicitly set to the first
+/// representable datetime by passing `None`.
+#[derive(Copy, Clone, Debug)]
+pub enum StartBound {
+    Now,
+    Min,
+    At(NaiveDateTimeWrapper),
+}
+
+impl StartBound {
+    /// Resolve the bound into a datetime, `now` is used for `StartBound::Now`.
+    pub fn resolve(self, now: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Self::Now => now,
+            Self::Min => NaiveDateTimeWrapper::min_py_value().into(),
+            Self::At(dt) => dt.0,
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for StartBound {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if ob.is_none() {
+            Ok(Self::Min)
+        } else {
+            Ok(Self::At(ob.extract()?))
+        }
+    }
+}
+
+// ---
+// --- NaiveDate wrapper
+// ---
+
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NaiveDateWrapper(NaiveDate);
+
+impl From<NaiveDateWrapper> for NaiveDate {
+    fn from(wrapper: NaiveDateWrapper) -> Self {
+        wrapper.0
+    }
+}
+
+impl From<NaiveDate> for NaiveDateWrapper {
+    fn from(date: NaiveDate) -> Self {
+        Self(date)
+    }
+}
+
+impl<'source> FromPyObject<'source> for NaiveDateWrapper {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let py_date: &PyDate = ob.downcast()?;
+        Ok(NaiveDate::from_ymd(
+            py_date.get_year(),
+            py_date.get_month().into(),
+            py_date.get_day().into(),
+        )
+        .into())
+    }
+}
+
+impl<'p> IntoPy<PyResult<Py<PyDate>>> for NaiveDateWrapper {
+    fn into_py(self, py: Python<'_>) -> PyResult<Py<PyDate>> {
+        Ok(PyDate::new(
+            py,
+            self.0.year(),
+            self.0.month().try_into()?,
+            self.0.day().try_into()?,
+        )?
+        .into())
+    }
+}
+
+impl<'p> IntoPy<Py<PyAny>> for NaiveDateWrapper {
+    fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+        let result: PyResult<_> = self.into_py(py);
+        result
+            .expect("failed at converting Rust date to Python")
+            .into_py(py)
+    }
+}
+
+// ---
+// --- NaiveTime wrapper
+// ---
+
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct NaiveTimeWrapper(NaiveTime);
+
+impl From<NaiveTimeWrapper> for NaiveTime {
+    fn from(wrapper: NaiveTimeWrapper) -> Self {
+        wrapper.0
+    }
+}
+
+impl From<NaiveTime> for NaiveTimeWrapper {
+    fn from(time: NaiveTime) -> Self {
+        Self(time)
+    }
+}
+
+impl NaiveTimeWrapper {
+    /// Last representable time of a day, which is used for ranges ending at
+    /// midnight.
+    pub fn end_of_day() -> NaiveTimeWrapper {
+        NaiveTime::from_hms_micro(23, 59, 59, 999_999).into()
+    }
+}
+
+impl<'source> FromPyObject<'source> for NaiveTimeWrapper {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let py_time: &PyTime = ob.downcast()?;
+        Ok(NaiveTime::from_hms(
+            py_time.get_hour().into(),
+            py_time.get_minute().into(),
+            py_time.get_second().into(),
+        )
+        .into())
+    }
+}
+
+impl<'p> IntoPy<PyResult<Py<PyTime>>> for NaiveTimeWrapper {
+    fn into_py(self, py: Python<'_>) -> PyResult<Py<PyTime>> {
+        Ok(PyTime::new(
+            py,
+            self.0.hour().try_into()?,
+            self.0.minute().try_into()?,
+            self.0.second().try_into()?,
+            self.0.nanosecond() / 1000,
+            None,
+        )?
+        .into())
+    }
+}
+
+impl<'p> IntoPy<Py<PyAny>> for NaiveTimeWrapper {
+    fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+        let result: PyResult<_> = self.into_py(py);
+        result
+            .expect("failed at converting Rust time to Python")
+            .into_py(py)
+    }
+}
+
+// ---
+// --- Duration wrapper
+// ---
+
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct DurationWrapper(Duration);
+
+impl From<DurationWrapper> for Duration {
+    fn from(wrapper: DurationWrapper) -> Self {
+        wrapper.0
+    }
+}
+
+impl From<Duration> for DurationWrapper {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl<'source> FromPyObject<'source> for DurationWrapper {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let py_delta: &PyDelta = ob.downcast()?;
+        Ok(Self(
+            Duration::days(py_delta.get_days().into())
+                + Duration::seconds(py_delta.get_seconds().into())
+                + Duration::microseconds(py_delta.get_microseconds().into()),
+        ))
+    }
+}
+
+impl<'p> IntoPy<PyResult<Py<PyDelta>>> for DurationWrapper {
+    fn into_py(self, py: Python<'_>) -> PyResult<Py<PyDelta>> {
+        let days = self.0.num_days();
+        let seconds = (self.0 - Duration::days(days)).num_seconds();
+        let microseconds = (self.0 - Duration::days(days) - Duration::seconds(seconds))
+            .num_microseconds()
+            .unwrap_or(0);
+
+        Ok(PyDelta::new(
+            py,
+            days.try_into()?,
+            seconds.try_into()?,
+            microseconds.try_into()?,
+            true,
+        )?
+        .into())
+    }
+}
+
+impl<'p> IntoPy<Py<PyAny>> for DurationWrapper {
+    fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+        let result: PyResult<_> = self.into_py(py);
+        result
+            .expect("failed at converting Rust duration to Python")
+            .into_py(py)
+    }
+}
+
 // ---
 // --- RangeIterator
 // ---
 
+/// Get owned copies of comments, if `normalize` is set they are trimmed and
+/// their sequences of whitespaces are collapsed into a single space.
+pub fn copy_comments<'c>(
+    comments: impl IntoIterator<Item = &'c str>,
+    normalize: bool,
+) -> Vec<String> {
+    comments
+        .into_iter()
+        .map(|comment| {
+            if normalize {
+                comment.split_whitespace().collect::<Vec<_>>().join(" ")
+            } else {
+                comment.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Owned version of the tuples yielded by `RangeIterator`.
+pub type IntervalTuple = (
+    NaiveDateTimeWrapper,
+    NaiveDateTimeWrapper,
+    State,
+    Vec<String>,
+);
+
+pub fn interval_tuple(dt_range: DateTimeRange, normalize_comments: bool) -> IntervalTuple {
+    (
+        dt_range.range.start.into(),
+        dt_range.range.end.into(),
+        dt_range.kind.into(),
+        copy_comments(dt_range.comments, normalize_comments),
+    )
+}
+
+/// Get the first part of a range that is between clock times `window` of a
+/// day, along with the start of the rest of the range if it can overlap with
+/// the window of following days.
+fn first_daily_window_part(
+    range: &Range<NaiveDateTime>,
+    window: (NaiveTime, NaiveTime),
+) -> Option<(Range<NaiveDateTime>, Option<NaiveDateTime>)> {
+    let (window_start, window_end) = window;
+    let mut date = range.start.date();
+
+    if date.and_time(window_end) <= range.start {
+        date = date.succ();
+    }
+
+    let start = max(range.start, date.and_time(window_start));
+    let end = min(range.end, date.and_time(window_end));
+
+    if start >= end {
+        return None;
+    }
+
+    let rest = Some(date.and_time(window_end)).filter(|rest| *rest < range.end);
+    Some((start..end, rest))
+}
+
+/// Split a range into its parts that are between clock times `window` of
+/// each day, parts are computed lazily.
+pub fn split_daily_window(
+    range: Range<NaiveDateTime>,
+    window: (NaiveTime, NaiveTime),
+) -> impl Iterator<Item = Range<NaiveDateTime>> {
+    let mut rest = Some(range);
+
+    std::iter::from_fn(move || {
+        let range = rest.take()?;
+        let (part, rest_start) = first_daily_window_part(&range, window)?;
+        rest = rest_start.map(|start| start..range.end);
+        Some(part)
+    })
+}
+
 #[pyclass(unsendable)]
 pub struct RangeIterator {
-    _td: Arc<time_domain::TimeDomain>,
+    _td: Arc<Domain>,
     iter: Box<dyn Iterator<Item = DateTimeRange<'static>>>,
+    translate: Option<PyObject>,
+    translations: HashMap<String, String>,
+    structured: bool,
+    comment_contains: Option<String>,
+    normalize_comments: bool,
+    resume_from: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+    inclusive_end: bool,
+    daily_window: Option<(NaiveTime, NaiveTime)>,
+    /// Rest of a range which was only partially yielded, as it extends past
+    /// the daily window.
+    pending: Option<DateTimeRange<'static>>,
 }
 
 impl RangeIterator {
-    pub fn new(
-        td: Arc<time_domain::TimeDomain>,
-        start: NaiveDateTime,
-        end: Option<NaiveDateTime>,
-    ) -> Self {
-        let iter: Box<dyn Iterator<Item = DateTimeRange>> = {
-            if let Some(end) = end {
-                Box::new(td.iter_range(start, end)) as _
-            } else {
-                Box::new(td.iter_from(start))
-            }
-        };
+    pub fn new(td: Arc<Domain>, start: NaiveDateTime, end: Option<NaiveDateTime>) -> Self {
+        let iter: Box<dyn Iterator<Item = DateTimeRange>> = Box::new(td.iter_from(start));
 
         // This transmute will only change the lifetime specifier for resulting
         // iterator items.
@@ -156,7 +545,240 @@ impl RangeIterator {
         // TODO: there is probably a solution less agressive than transmute?
         let iter = unsafe { std::mem::transmute(iter) };
 
-        Self { _td: td, iter }
+        Self {
+            _td: td,
+            iter,
+            translate: None,
+            translations: HashMap::new(),
+            structured: false,
+            comment_contains: None,
+            normalize_comments: false,
+            resume_from: start,
+            end,
+            inclusive_end: true,
+            daily_window: None,
+            pending: None,
+        }
+    }
+
+    /// Map comments through a Python callable before they are returned.
+    pub fn with_translate(mut self, translate: Option<PyObject>) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    /// Yield `Interval` objects instead of tuples.
+    pub fn with_structured(mut self, structured: bool) -> Self {
+        self.structured = structured;
+        self
+    }
+
+    /// Only yield ranges having a comment that contains given pattern.
+    pub fn with_comment_contains(mut self, pattern: Option<String>) -> Self {
+        self.comment_contains = pattern;
+        self
+    }
+
+    /// Normalize whitespaces of comments before they are translated.
+    pub fn with_normalize_comments(mut self, normalize_comments: bool) -> Self {
+        self.normalize_comments = normalize_comments;
+        self
+    }
+
+    /// Clip the last range to the end of the iterator if it extends past it,
+    /// if this is disabled, the range is dropped instead.
+    pub fn with_inclusive_end(mut self, inclusive_end: bool) -> Self {
+        self.inclusive_end = inclusive_end;
+        self
+    }
+
+    /// Only yield the parts of ranges that are between given clock times of
+    /// each day, the start of the window must be before its end.
+    pub fn with_daily_window(mut self, daily_window: Option<(NaiveTime, NaiveTime)>) -> Self {
+        self.daily_window = daily_window;
+        self
+    }
+
+    /// Apply the translation callback, which is only called once for each
+    /// distinct comment.
+    fn translate_comment(&mut self, py: Python, comment: &str) -> PyResult<String> {
+        let translate = match &self.translate {
+            Some(translate) => translate,
+            None => return Ok(comment.to_string()),
+        };
+
+        if let Some(translated) = self.translations.get(comment) {
+            return Ok(translated.clone());
+        }
+
+        let translated: String = translate.call1(py, (comment,))?.extract(py)?;
+        self.translations
+            .insert(comment.to_string(), translated.clone());
+        Ok(translated)
+    }
+
+    /// Pull the next range from the inner iterator, clipped to the end of the
+    /// iterator.
+    fn pull_source_range(&mut self) -> Option<DateTimeRange<'static>> {
+        let mut dt_range = self.iter.next()?;
+
+        if let Some(end) = self.end {
+            if dt_range.range.start >= end || (dt_range.range.end > end && !self.inclusive_end) {
+                self.iter = Box::new(std::iter::empty());
+                return None;
+            }
+
+            dt_range.range.end = min(dt_range.range.end, end);
+        }
+
+        Some(dt_range)
+    }
+
+    /// Pull the next range, clipped to the end of the iterator and to the
+    /// daily window. Ranges are split one day at a time.
+    fn pull_range(&mut self) -> Option<DateTimeRange<'static>> {
+        let dt_range = loop {
+            let mut dt_range = match self.pending.take() {
+                Some(dt_range) => dt_range,
+                None => self.pull_source_range()?,
+            };
+
+            let window = match self.daily_window {
+                Some(window) => window,
+                None => break dt_range,
+            };
+
+            if let Some((part, rest)) = first_daily_window_part(&dt_range.range, window) {
+                if let Some(rest) = rest {
+                    let mut remaining = dt_range.clone();
+                    remaining.range = rest..dt_range.range.end;
+                    self.pending = Some(remaining);
+                }
+
+                dt_range.range = part;
+                break dt_range;
+            }
+        };
+
+        self.resume_from = dt_range.range.end;
+        Some(dt_range)
+    }
+
+    /// Get the next range of the iterator without converting it to Python.
+    pub fn next_range(&mut self) -> Option<DateTimeRange<'_>> {
+        self.pull_range()
+    }
+
+    /// Check if the domain may be open after `time`, within the end of the
+    /// iterator.
+    pub fn may_open_after(&self, time: NaiveDateTime) -> bool {
+        self.end.map_or(true, |end| time < end) && self._td.may_open_after(time)
+    }
+
+    /// Get the next interval of the iterator, with all options applied.
+    pub fn next_interval(&mut self, py: Python) -> PyResult<Option<IntervalTuple>> {
+        let dt_range = loop {
+            let dt_range = match self.pull_range() {
+                Some(dt_range) => dt_range,
+                None => return Ok(None),
+            };
+
+            let is_matching = match &self.comment_contains {
+                Some(pattern) => dt_range
+                    .comments
+                    .iter()
+                    .any(|c| c.contains(pattern.as_str())),
+                None => true,
+            };
+
+            if is_matching {
+                break dt_range;
+            }
+        };
+
+        let comments = copy_comments(dt_range.comments, self.normalize_comments)
+            .iter()
+            .map(|comment| self.translate_comment(py, comment))
+            .collect::<PyResult<_>>()?;
+
+        Ok(Some((
+            dt_range.range.start.into(),
+            dt_range.range.end.into(),
+            dt_range.kind.into(),
+            comments,
+        )))
+    }
+
+    /// Convert an interval to the Python representation selected for this
+    /// iterator.
+    pub fn interval_into_py(&self, py: Python, interval: IntervalTuple) -> PyObject {
+        if self.structured {
+            Interval::from(interval).into_py(py)
+        } else {
+            interval.into_py(py)
+        }
+    }
+}
+
+#[pymethods]
+impl RangeIterator {
+    /// Get a token that can be passed as `start` to `OpeningHours.intervals`
+    /// to resume the iteration after the last interval consumed.
+    ///
+    /// The token is an ISO 8601 datetime string: `YYYY-MM-DDTHH:MM:SS`.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo 10:00-12:00")
+    /// >>> intervals = oh.intervals(datetime(2020, 11, 2), datetime(2020, 11, 3))
+    /// >>> next(intervals)[:3]
+    /// (datetime.datetime(2020, 11, 2, 0, 0), datetime.datetime(2020, 11, 2, 10, 0), 'closed')
+    /// >>> token = intervals.resume_token()
+    /// >>> token
+    /// '2020-11-02T10:00:00'
+    /// >>> next(oh.intervals(token, datetime(2020, 11, 3)))[:3]
+    /// (datetime.datetime(2020, 11, 2, 10, 0), datetime.datetime(2020, 11, 2, 12, 0), 'open')
+    #[text_signature = "(self, /)"]
+    fn resume_token(&self) -> String {
+        self.resume_from.format("%Y-%m-%dT%H:%M:%S").to_string()
+    }
+
+    /// Consume intervals until `predicate` returns true and return them as a
+    /// list, the interval for which `predicate` returned true is included as
+    /// the last item.
+    ///
+    /// If `predicate` never returns true, all remaining intervals are
+    /// returned.
+    ///
+    /// Parameters
+    /// ----------
+    /// predicate : Callable[[Any], bool]
+    ///     Function called on each interval, in the format yielded by the
+    ///     iterator.
+    ///
+    /// Examples
+    /// --------
+    /// >>> oh = OpeningHours("Mo 10:00-12:00")
+    /// >>> intervals = oh.intervals(datetime(2020, 11, 2, 8), structured=True)
+    /// >>> [i.state for i in intervals.take_until(lambda i: i.state == "open")]
+    /// ['closed', 'open']
+    #[text_signature = "(self, predicate, /)"]
+    fn take_until(&mut self, py: Python, predicate: PyObject) -> PyResult<Vec<PyObject>> {
+        let mut result = Vec::new();
+
+        while let Some(interval) = self.next_interval(py)? {
+            let interval = self.interval_into_py(py, interval);
+            let stop: bool = predicate
+                .call1(py, (interval.clone_ref(py),))?
+                .extract(py)?;
+            result.push(interval);
+
+            if stop {
+                break;
+            }
+        }
+
+        Ok(result)
     }
 }
 
@@ -166,20 +788,506 @@ impl PyIterProtocol for RangeIterator {
         slf.into()
     }
 
-    fn __next__(
-        mut slf: PyRefMut<Self>,
-    ) -> Option<(
-        NaiveDateTimeWrapper,
-        NaiveDateTimeWrapper,
-        State,
-        Vec<&'p str>,
-    )> {
-        let dt_range = slf.iter.next()?;
-        Some((
-            dt_range.range.start.into(),
-            dt_range.range.end.into(),
-            dt_range.kind.into(),
-            dt_range.comments,
-        ))
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+
+        Ok(match slf.next_interval(py)? {
+            Some(interval) => Some(slf.interval_into_py(py, interval)),
+            None => None,
+        })
+    }
+}
+
+// ---
+// --- Interval
+// ---
+
+/// A time interval of consistent state.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Interval {
+    #[pyo3(get)]
+    pub start: NaiveDateTimeWrapper,
+    #[pyo3(get)]
+    pub end: NaiveDateTimeWrapper,
+    #[pyo3(get)]
+    pub state: State,
+    #[pyo3(get)]
+    pub comments: Vec<String>,
+}
+
+impl From<IntervalTuple> for Interval {
+    fn from((start, end, state, comments): IntervalTuple) -> Self {
+        Self {
+            start,
+            end,
+            state,
+            comments,
+        }
+    }
+}
+
+#[pymethods]
+impl Interval {
+    /// Duration of the interval, or `None` if it never ends.
+    #[getter]
+    fn duration(&self) -> Option<DurationWrapper> {
+        if self.end >= NaiveDateTimeWrapper::max_py_value() {
+            return None;
+        }
+
+        Some((self.end.0 - self.start.0).into())
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for Interval {
+    fn __repr__(&self) -> String {
+        format!(
+            "Interval(start={}, end={}, state={:?}, comments={:?})",
+            self.start.0,
+            self.end.0,
+            self.state.as_str(),
+            self.comments,
+        )
+    }
+}
+
+// ---
+// --- StateTracker
+// ---
+
+/// Keep track of the state of consecutive ranges to detect changes of state.
+///
+/// A range starts with a change of state if it is not empty and its state
+/// differs from the last range tracked: boundaries where only the comments
+/// change are not changes of state.
+///
+/// If unknown state is collapsed, it is tracked as closed state so that only
+/// changes between open and not open are detected.
+#[derive(Default)]
+pub struct StateTracker {
+    kind: Option<RuleKind>,
+    collapse_unknown: bool,
+}
+
+impl StateTracker {
+    pub fn with_collapse_unknown(mut self, collapse_unknown: bool) -> Self {
+        self.collapse_unknown = collapse_unknown;
+        self.kind = self.kind.map(|kind| self.tracked_kind(kind));
+        self
+    }
+
+    fn tracked_kind(&self, kind: RuleKind) -> RuleKind {
+        match kind {
+            RuleKind::Unknown if self.collapse_unknown => RuleKind::Closed,
+            kind => kind,
+        }
+    }
+
+    /// Track the next range and check if it starts with a new state, the
+    /// first non-empty range always does.
+    pub fn track(&mut self, range: &Range<NaiveDateTime>, kind: RuleKind) -> bool {
+        let kind = self.tracked_kind(kind);
+
+        if range.start >= range.end || self.kind == Some(kind) {
+            return false;
+        }
+
+        self.kind = Some(kind);
+        true
+    }
+
+    /// Check if the state will never change again, given if the domain may
+    /// still be open: when unknown state is collapsed, a closed state can
+    /// only change to open. This bounds the scan of schedules that are never
+    /// open again.
+    pub fn is_final(&self, may_open: impl FnOnce() -> bool) -> bool {
+        self.collapse_unknown && self.kind == Some(RuleKind::Closed) && !may_open()
+    }
+
+    /// State of the last range that started with a new state.
+    pub fn kind(&self) -> Option<RuleKind> {
+        self.kind
+    }
+}
+
+/// Only keep the ranges starting with a new state, as defined by
+/// `StateTracker`. Apart from the first one, the start of each range yielded
+/// is a change of state.
+pub fn state_changes<'c>(
+    ranges: impl Iterator<Item = DateTimeRange<'c>>,
+) -> impl Iterator<Item = DateTimeRange<'c>> {
+    let mut tracker = StateTracker::default();
+    ranges.filter(move |dt_range| tracker.track(&dt_range.range, dt_range.kind))
+}
+
+// ---
+// --- Transition
+// ---
+
+/// A change of state, with the comments attached to the new state.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Transition {
+    #[pyo3(get)]
+    pub at: NaiveDateTimeWrapper,
+    #[pyo3(get)]
+    pub from_state: State,
+    #[pyo3(get)]
+    pub to_state: State,
+    #[pyo3(get)]
+    pub comments: Vec<String>,
+}
+
+#[pyproto]
+impl PyObjectProtocol for Transition {
+    fn __repr__(&self) -> String {
+        format!(
+            "Transition(at={}, from_state={:?}, to_state={:?}, comments={:?})",
+            self.at.0,
+            self.from_state.as_str(),
+            self.to_state.as_str(),
+            self.comments,
+        )
+    }
+}
+
+// ---
+// --- Summary
+// ---
+
+/// Summary of the states over a time window.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Summary {
+    /// Either "open", "closed" or "unknown" if the state is the same during
+    /// the whole window, or "mixed" otherwise.
+    #[pyo3(get)]
+    pub state: &'static str,
+    #[pyo3(get)]
+    pub comments: Vec<String>,
+}
+
+#[pyproto]
+impl PyObjectProtocol for Summary {
+    fn __repr__(&self) -> String {
+        format!(
+            "Summary(state={:?}, comments={:?})",
+            self.state, self.comments,
+        )
+    }
+}
+
+// ---
+// --- ChangeIterator
+// ---
+
+#[pyclass(unsendable)]
+pub struct ChangeIterator {
+    ranges: RangeIterator,
+    tracker: StateTracker,
+    structured: bool,
+    normalize_comments: bool,
+}
+
+impl ChangeIterator {
+    pub fn new(mut ranges: RangeIterator) -> Self {
+        let mut tracker = StateTracker::default();
+
+        while let Some(dt_range) = ranges.next_range() {
+            if tracker.track(&dt_range.range, dt_range.kind) {
+                break;
+            }
+        }
+
+        Self {
+            ranges,
+            tracker,
+            structured: false,
+            normalize_comments: false,
+        }
+    }
+
+    /// Yield `Transition` objects instead of tuples.
+    pub fn with_structured(mut self, structured: bool) -> Self {
+        self.structured = structured;
+        self
+    }
+
+    /// Normalize whitespaces of comments attached to transitions.
+    pub fn with_normalize_comments(mut self, normalize_comments: bool) -> Self {
+        self.normalize_comments = normalize_comments;
+        self
+    }
+
+    /// Consider unknown state as closed, so that only changes between open
+    /// and not open are reported.
+    pub fn with_collapse_unknown(mut self, collapse_unknown: bool) -> Self {
+        self.tracker = self.tracker.with_collapse_unknown(collapse_unknown);
+        self
+    }
+
+    /// Get the next change of state without converting it to Python, the
+    /// tracker is reset once all ranges have been consumed.
+    pub fn next_transition(&mut self) -> Option<Transition> {
+        let from_kind = self.tracker.kind()?;
+
+        while let Some(dt_range) = self.ranges.next_range() {
+            let end = dt_range.range.end;
+
+            if self.tracker.track(&dt_range.range, dt_range.kind) {
+                return Some(Transition {
+                    at: dt_range.range.start.into(),
+                    from_state: from_kind.into(),
+                    to_state: self.tracker.kind()?.into(),
+                    comments: copy_comments(dt_range.comments, self.normalize_comments),
+                });
+            }
+
+            if self.tracker.is_final(|| self.ranges.may_open_after(end)) {
+                break;
+            }
+        }
+
+        self.tracker = StateTracker::default();
+        None
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for ChangeIterator {
+    fn __iter__(slf: PyRef<Self>) -> Py<ChangeIterator> {
+        slf.into()
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<PyObject> {
+        let py = slf.py();
+        let transition = slf.next_transition()?;
+
+        Some({
+            if slf.structured {
+                transition.into_py(py)
+            } else {
+                (transition.at, transition.from_state, transition.to_state).into_py(py)
+            }
+        })
+    }
+}
+
+// ---
+// --- BatchIterator
+// ---
+
+/// Iterator over lists of intervals, which amortizes the cost of crossing the
+/// boundary with Python when iterating over large windows.
+#[pyclass(unsendable)]
+pub struct BatchIterator {
+    ranges: RangeIterator,
+    batch_size: usize,
+}
+
+impl BatchIterator {
+    pub fn new(ranges: RangeIterator, batch_size: usize) -> Self {
+        Self { ranges, batch_size }
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for BatchIterator {
+    fn __iter__(slf: PyRef<Self>) -> Py<BatchIterator> {
+        slf.into()
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<Vec<PyObject>>> {
+        let py = slf.py();
+        let mut batch = Vec::with_capacity(slf.batch_size);
+
+        while batch.len() < slf.batch_size {
+            match slf.ranges.next_interval(py)? {
+                Some(interval) => batch.push(slf.ranges.interval_into_py(py, interval)),
+                None => break,
+            }
+        }
+
+        Ok(if batch.is_empty() { None } else { Some(batch) })
+    }
+}
+
+// ---
+// --- WeekIterator
+// ---
+
+/// Schedule of a week, as the date of its Monday and the open ranges of each
+/// weekday, from 0 for Monday to 6 for Sunday.
+pub type WeekSchedule = (
+    NaiveDateWrapper,
+    BTreeMap<u32, Vec<(NaiveTimeWrapper, NaiveTimeWrapper)>>,
+);
+
+/// Iterator over the schedules of consecutive weeks, which are only computed
+/// when they are requested.
+#[pyclass(unsendable)]
+pub struct WeekIterator {
+    weeks: Box<dyn Iterator<Item = WeekSchedule>>,
+}
+
+impl WeekIterator {
+    pub fn new(weeks: impl Iterator<Item = WeekSchedule> + 'static) -> Self {
+        Self {
+            weeks: Box::new(weeks),
+        }
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for WeekIterator {
+    fn __iter__(slf: PyRef<Self>) -> Py<WeekIterator> {
+        slf.into()
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<WeekSchedule> {
+        slf.weeks.next()
+    }
+}
+
+// ---
+// --- BoundaryIterator
+// ---
+
+/// Iterator over the datetimes of successive changes of state, which are
+/// guaranteed to be strictly increasing.
+#[pyclass(unsendable)]
+pub struct BoundaryIterator {
+    changes: ChangeIterator,
+    last_boundary: NaiveDateTime,
+}
+
+impl BoundaryIterator {
+    pub fn new(changes: ChangeIterator, start: NaiveDateTime) -> Self {
+        Self {
+            changes,
+            last_boundary: start,
+        }
+    }
+
+    /// Get the next change of state, a change is never reported twice at
+    /// the same time.
+    pub fn next_boundary(&mut self) -> Option<NaiveDateTime> {
+        while let Some(transition) = self.changes.next_transition() {
+            let at = transition.at.into();
+
+            if at > self.last_boundary {
+                self.last_boundary = at;
+                return Some(at);
+            }
+        }
+
+        None
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for BoundaryIterator {
+    fn __iter__(slf: PyRef<Self>) -> Py<BoundaryIterator> {
+        slf.into()
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<NaiveDateTimeWrapper> {
+        slf.next_boundary().map(Into::into)
+    }
+}
+
+// ---
+// --- EventIterator
+// ---
+
+/// Iterator over the moments the state becomes open, or stops being open,
+/// given as tuples `(datetime, comments)`.
+///
+/// Unknown state is tracked as closed state, as defined by `StateTracker`,
+/// so that only changes from or to a state that is not open are reported.
+#[pyclass(unsendable)]
+pub struct EventIterator {
+    ranges: RangeIterator,
+    tracker: StateTracker,
+    normalize_comments: bool,
+    closing: bool,
+    /// Comments of the last open range, which are attached to the closing
+    /// event that ends it.
+    open_comments: Vec<String>,
+}
+
+impl EventIterator {
+    pub fn new(ranges: RangeIterator) -> Self {
+        Self {
+            ranges,
+            tracker: StateTracker::default().with_collapse_unknown(true),
+            normalize_comments: false,
+            closing: false,
+            open_comments: Vec::new(),
+        }
+    }
+
+    /// Yield the moments the state stops being open, with the comments of
+    /// the range that ends, instead of the moments it becomes open.
+    pub fn with_closing(mut self, closing: bool) -> Self {
+        self.closing = closing;
+        self
+    }
+
+    /// Normalize whitespaces of comments attached to events.
+    pub fn with_normalize_comments(mut self, normalize_comments: bool) -> Self {
+        self.normalize_comments = normalize_comments;
+        self
+    }
+
+    /// Get the next event without converting it to Python, the state of the
+    /// first range is never reported as an event.
+    pub fn next_event(&mut self) -> Option<(NaiveDateTimeWrapper, Vec<String>)> {
+        while let Some(dt_range) = self.ranges.next_range() {
+            let end = dt_range.range.end;
+            let from_kind = self.tracker.kind();
+
+            let is_event = self.tracker.track(&dt_range.range, dt_range.kind)
+                && from_kind.is_some()
+                && (dt_range.kind == RuleKind::Open) != self.closing;
+
+            if is_event && self.closing {
+                let comments = std::mem::take(&mut self.open_comments);
+                return Some((dt_range.range.start.into(), comments));
+            }
+
+            if is_event {
+                return Some((
+                    dt_range.range.start.into(),
+                    copy_comments(dt_range.comments, self.normalize_comments),
+                ));
+            }
+
+            if self.closing
+                && dt_range.kind == RuleKind::Open
+                && dt_range.range.start < dt_range.range.end
+            {
+                self.open_comments = copy_comments(dt_range.comments, self.normalize_comments);
+            }
+
+            if self.tracker.is_final(|| self.ranges.may_open_after(end)) {
+                break;
+            }
+        }
+
+        None
+    }
+}
+
+#[pyproto]
+impl PyIterProtocol for EventIterator {
+    fn __iter__(slf: PyRef<Self>) -> Py<EventIterator> {
+        slf.into()
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> Option<(NaiveDateTimeWrapper, Vec<String>)> {
+        slf.next_event()
     }
 }
@@ -0,0 +1,20 @@
+from datetime import datetime
+from itertools import islice
+
+from opening_hours import OpeningHours
+
+
+def test_iter_changes_from_is_strictly_increasing():
+    # Empty spans and spans ending where the next one starts are boundaries
+    # without duration, which must not be yielded twice.
+    oh = OpeningHours("Mo 10:00-10:00,12:00-14:00, Mo 14:00-16:00 unknown; Tu 00:00-24:00")
+    changes = list(islice(oh.iter_changes_from(datetime(2020, 11, 2)), 20))
+
+    assert len(changes) == 20
+    assert all(previous < following for previous, following in zip(changes, changes[1:]))
+    assert changes.count(datetime(2020, 11, 2, 14)) == 1
+
+
+def test_iter_changes_from_a_constant_state():
+    oh = OpeningHours("24/7")
+    assert list(oh.iter_changes_from(datetime(2020, 11, 2))) == []
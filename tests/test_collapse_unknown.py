@@ -0,0 +1,36 @@
+from datetime import datetime
+
+from opening_hours import OpeningHours
+
+START = datetime(2020, 11, 6, 8)
+
+
+def test_unknown_is_closed():
+    oh = OpeningHours("Mo-Fr 10:00-18:00; Sa 10:00-12:00 unknown")
+
+    assert oh.next_change(datetime(2020, 11, 6, 20)) == datetime(2020, 11, 7, 10)
+    assert oh.next_change(datetime(2020, 11, 6, 20), collapse_unknown=True) == datetime(
+        2020, 11, 9, 10
+    )
+    assert list(oh.changes(START, datetime(2020, 11, 8), collapse_unknown=True)) == [
+        (datetime(2020, 11, 6, 10), "closed", "open"),
+        (datetime(2020, 11, 6, 18), "open", "closed"),
+    ]
+
+
+def test_never_open():
+    oh = OpeningHours("Mo 10:00-12:00 unknown")
+
+    assert oh.next_change(START, collapse_unknown=True) is None
+    assert list(oh.changes(START, collapse_unknown=True)) == []
+
+
+def test_never_open_after_last_year():
+    oh = OpeningHours("2020 Mo 10:00-12:00; Tu 10:00-12:00 unknown")
+    start = datetime(2020, 12, 29)
+
+    assert oh.next_change(start, collapse_unknown=True) is None
+    assert list(oh.changes(datetime(2020, 12, 27), collapse_unknown=True)) == [
+        (datetime(2020, 12, 28, 10), "closed", "open"),
+        (datetime(2020, 12, 28, 12), "open", "closed"),
+    ]
@@ -0,0 +1,52 @@
+from datetime import datetime, timedelta
+
+import pytest
+
+from opening_hours import OpeningHours
+
+EXPRESSION = "Mo-Fr 09:00-12:00,14:00-17:00"
+
+
+def test_formatting_is_ignored():
+    oh = OpeningHours(EXPRESSION)
+    other = OpeningHours("Mo-Fr  09:00-12:00, 14:00-17:00")
+
+    assert oh == other
+    assert not oh != other
+    assert hash(oh) == hash(other)
+
+
+def test_different_expressions():
+    assert OpeningHours(EXPRESSION) != OpeningHours("Mo-Fr 09:00-17:00")
+
+
+def test_shift_and_window_are_compared():
+    oh = OpeningHours(EXPRESSION)
+    shifted = oh.shift(timedelta(hours=1))
+    restricted = oh.restrict(datetime(2020, 11, 2), datetime(2020, 11, 3))
+
+    assert oh != shifted
+    assert oh != restricted
+    assert shifted == oh.shift(timedelta(hours=1))
+    assert hash(shifted) == hash(oh.shift(timedelta(hours=1)))
+
+
+def test_usable_in_sets():
+    assert len({OpeningHours(EXPRESSION), OpeningHours(EXPRESSION)}) == 1
+
+
+def test_other_types():
+    oh = OpeningHours(EXPRESSION)
+
+    assert oh != EXPRESSION
+    assert not oh == EXPRESSION
+    assert oh.__eq__(EXPRESSION) is NotImplemented
+
+
+def test_ordering_is_not_supported():
+    oh = OpeningHours(EXPRESSION)
+
+    assert oh.__lt__(oh) is NotImplemented
+
+    with pytest.raises(TypeError):
+        oh < oh
@@ -0,0 +1,38 @@
+import copy
+from datetime import datetime, timedelta
+
+import pytest
+
+from opening_hours import OpeningHours
+
+OPEN = datetime(2020, 11, 2, 11)
+CLOSED = datetime(2020, 11, 2, 13)
+
+
+@pytest.mark.parametrize("copy_function", [copy.copy, copy.deepcopy])
+def test_copies_are_independent(copy_function):
+    oh = OpeningHours("Mo-Fr 09:00-12:00,14:00-17:00")
+    copied = copy_function(oh)
+
+    assert copied is not oh
+    assert copied.is_open(OPEN)
+    assert not copied.is_open(CLOSED)
+    assert oh.state(OPEN) == copied.state(OPEN) == "open"
+    assert oh.state(CLOSED) == copied.state(CLOSED) == "closed"
+
+
+@pytest.mark.parametrize("copy_function", [copy.copy, copy.deepcopy])
+def test_copies_keep_the_shift(copy_function):
+    oh = OpeningHours("Mo-Fr 09:00-12:00,14:00-17:00").shift(timedelta(hours=2))
+    copied = copy_function(oh)
+
+    assert copied.state(CLOSED) == "open"
+    assert copied.next_change(CLOSED) == oh.next_change(CLOSED)
+
+
+def test_deepcopy_keeps_shared_references():
+    oh = OpeningHours("Mo 10:00-12:00")
+    first, second = copy.deepcopy([oh, oh])
+
+    assert first is second
+    assert first is not oh
@@ -0,0 +1,17 @@
+from datetime import datetime
+
+from opening_hours import OpeningHours
+
+
+def test_intervals_tile_the_timeline():
+    oh = OpeningHours("Mo-Fr 10:00-12:00,14:00-18:00; Sa 10:00-12:00 unknown")
+    start, end = datetime(2020, 11, 2), datetime(2020, 11, 16)
+    intervals = list(oh.intervals(start, end))
+
+    assert intervals[0][0] == start
+    assert intervals[-1][1] == end
+    assert {state for _, _, state, _ in intervals} == {"open", "closed", "unknown"}
+
+    for previous, following in zip(intervals, intervals[1:]):
+        assert previous[0] < previous[1]
+        assert previous[1] == following[0]
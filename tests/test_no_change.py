@@ -0,0 +1,34 @@
+from datetime import datetime
+
+import pytest
+
+from opening_hours import OpeningHours
+
+START = datetime(2020, 11, 2)
+
+
+def test_intervals_of_a_constant_state_terminate():
+    oh = OpeningHours("24/7")
+
+    assert list(oh.intervals(START)) == [(START, None, "open", [])]
+    assert list(oh.intervals(START, datetime(2020, 11, 3))) == [
+        (START, datetime(2020, 11, 3), "open", [])
+    ]
+
+
+def test_changes_of_a_constant_state_terminate():
+    oh = OpeningHours("24/7")
+
+    assert list(oh.changes(START)) == []
+    assert list(oh.changes(START, datetime(2020, 11, 3))) == []
+
+
+def test_next_change_of_a_constant_state():
+    oh = OpeningHours("24/7")
+
+    assert oh.next_change(START) is None
+    assert oh.next_change(START, no_change="none") is None
+    assert oh.next_change(START, no_change="max") == datetime.max
+
+    with pytest.raises(ValueError):
+        oh.next_change(START, no_change="raise")
@@ -0,0 +1,37 @@
+from datetime import datetime, timedelta
+
+from opening_hours import OpeningHours
+
+START = datetime(2020, 11, 2)
+END = datetime(2020, 11, 3)
+
+
+def at(hour):
+    return START.replace(hour=hour)
+
+
+def test_merge_closed_gaps():
+    oh = OpeningHours("Mo 10:00-12:00,13:00-18:00")
+
+    assert oh.opening_intervals(START, END) == [(at(10), at(12)), (at(13), at(18))]
+    assert oh.opening_intervals(START, END, merge_gaps=timedelta(hours=2)) == [
+        (at(10), at(18))
+    ]
+
+
+def test_long_gaps_are_kept():
+    oh = OpeningHours("Mo 10:00-12:00,13:00-18:00")
+
+    assert oh.opening_intervals(START, END, merge_gaps=timedelta(minutes=30)) == [
+        (at(10), at(12)),
+        (at(13), at(18)),
+    ]
+
+
+def test_unknown_gaps_are_not_merged():
+    oh = OpeningHours("Mo 10:00-12:00,13:00-18:00, Mo 12:00-13:00 unknown")
+
+    assert oh.opening_intervals(START, END, merge_gaps=timedelta(hours=2)) == [
+        (at(10), at(12)),
+        (at(13), at(18)),
+    ]
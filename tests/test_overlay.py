@@ -0,0 +1,35 @@
+from datetime import datetime, timedelta
+
+import pytest
+
+from opening_hours import OpeningHours
+
+MONDAY = datetime(2020, 11, 2)
+TUESDAY = datetime(2020, 11, 3)
+
+
+def test_overlay_overrides_matched_days():
+    regular = OpeningHours("Mo-Fr 10:00-18:00")
+    oh = regular.overlay(OpeningHours("Mo 14:00-16:00"))
+
+    assert oh.state(MONDAY.replace(hour=11)) == "closed"
+    assert oh.state(MONDAY.replace(hour=15)) == "open"
+    assert oh.state(TUESDAY.replace(hour=11)) == "open"
+    assert regular.state(MONDAY.replace(hour=11)) == "open"
+
+
+def test_overlay_can_close_days():
+    oh = OpeningHours("Mo-Fr 10:00-18:00").overlay(OpeningHours("Mo off"))
+
+    assert oh.state(MONDAY.replace(hour=11)) == "closed"
+    assert oh.state(TUESDAY.replace(hour=11)) == "open"
+
+
+def test_overlay_rejects_shifted_or_restricted():
+    oh = OpeningHours("Mo-Fr 10:00-18:00")
+
+    with pytest.raises(ValueError):
+        oh.overlay(OpeningHours("Mo off").shift(timedelta(hours=1)))
+
+    with pytest.raises(ValueError):
+        oh.restrict(MONDAY, TUESDAY).overlay(OpeningHours("Mo off"))
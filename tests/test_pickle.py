@@ -0,0 +1,36 @@
+import pickle
+from datetime import datetime, timedelta
+
+from opening_hours import OpeningHours
+
+EXPRESSION = "Mo-Fr 09:00-12:00,14:00-17:00"
+
+
+def roundtrip(oh):
+    return pickle.loads(pickle.dumps(oh))
+
+
+def test_expression_is_kept():
+    oh = OpeningHours(EXPRESSION)
+    assert roundtrip(oh) == oh
+
+
+def test_corrections_are_kept():
+    oh = OpeningHours("Mo-Fr 9-17", tolerant=True)
+    unpickled = roundtrip(oh)
+
+    assert unpickled.corrections == oh.corrections
+    assert unpickled.is_open(datetime(2020, 11, 2, 10))
+
+
+def test_shift_and_window_are_kept():
+    oh = (
+        OpeningHours(EXPRESSION)
+        .shift(timedelta(hours=2))
+        .restrict(datetime(2020, 11, 2), datetime(2020, 11, 3))
+    )
+    unpickled = roundtrip(oh)
+
+    assert unpickled == oh
+    assert unpickled.state(datetime(2020, 11, 2, 13)) == "open"
+    assert unpickled.state(datetime(2020, 11, 3, 13)) == "closed"
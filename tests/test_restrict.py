@@ -0,0 +1,49 @@
+from datetime import datetime, timedelta
+
+from opening_hours import OpeningHours
+
+START = datetime(2020, 11, 2)
+END = datetime(2020, 11, 4)
+
+
+def test_restrict_is_closed_outside_the_window():
+    oh = OpeningHours("24/7").restrict(START, END)
+
+    assert oh.state(START - timedelta(minutes=1)) == "closed"
+    assert oh.state(START) == "open"
+    assert oh.state(END - timedelta(minutes=1)) == "open"
+    assert oh.state(END) == "closed"
+    assert oh.next_change(START - timedelta(days=1)) == START
+    assert oh.next_change(START) == END
+
+
+def test_restrict_merges_ranges_at_the_edges():
+    oh = OpeningHours("Mo-Fr 10:00-18:00").restrict(START, END)
+    day = timedelta(days=1)
+
+    assert list(oh.intervals(START - day, END + day)) == [
+        (START - day, START.replace(hour=10), "closed", []),
+        (START.replace(hour=10), START.replace(hour=18), "open", []),
+        (START.replace(hour=18), START.replace(day=3, hour=10), "closed", []),
+        (START.replace(day=3, hour=10), START.replace(day=3, hour=18), "open", []),
+        (START.replace(day=3, hour=18), END + day, "closed", []),
+    ]
+
+
+def test_restrictions_intersect():
+    oh = OpeningHours("24/7").restrict(START, END).restrict(START + timedelta(days=1), END)
+    assert oh.state(START) == "closed"
+    assert oh.state(START + timedelta(days=1)) == "open"
+
+
+def test_shift_moves_the_window():
+    oh = OpeningHours("24/7").restrict(START, END).shift(timedelta(hours=1))
+    assert oh.state(START) == "closed"
+    assert oh.state(END) == "open"
+
+
+def test_restrict_survives_json():
+    oh = OpeningHours("Mo-Fr 10:00-18:00").restrict(START, END)
+    rebuilt = OpeningHours.from_ast_json(oh.to_ast_json())
+    assert rebuilt.state(START.replace(hour=12)) == "open"
+    assert rebuilt.state(END.replace(hour=12)) == "closed"
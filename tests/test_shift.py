@@ -0,0 +1,51 @@
+"""
+Tests of the extension module, run with `pytest` once the module is
+installed, for example with `maturin develop`.
+"""
+
+from datetime import datetime, timedelta
+
+import pytest
+
+from opening_hours import OpeningHours
+
+MONDAY = datetime(2020, 11, 2)
+
+
+def test_shift_moves_states_and_changes():
+    oh = OpeningHours("Mo 10:00-12:00").shift(timedelta(hours=1))
+
+    assert oh.state(MONDAY.replace(hour=10, minute=30)) == "closed"
+    assert oh.state(MONDAY.replace(hour=12, minute=30)) == "open"
+    assert oh.next_change(MONDAY) == MONDAY.replace(hour=11)
+    assert list(oh.intervals(MONDAY, MONDAY + timedelta(days=1))) == [
+        (MONDAY, MONDAY.replace(hour=11), "closed", []),
+        (MONDAY.replace(hour=11), MONDAY.replace(hour=13), "open", []),
+        (MONDAY.replace(hour=13), MONDAY + timedelta(days=1), "closed", []),
+    ]
+
+
+def test_shift_across_midnight():
+    oh = OpeningHours("Mo 20:00-24:00").shift(timedelta(hours=-21))
+    assert oh.state(datetime(2020, 11, 1, 23, 30)) == "open"
+    assert oh.state(MONDAY.replace(hour=2, minute=30)) == "open"
+    assert oh.state(MONDAY.replace(hour=3, minute=30)) == "closed"
+
+
+def test_shifts_add_up():
+    oh = OpeningHours("Mo 10:00-12:00")
+    shifted = oh.shift(timedelta(minutes=30)).shift(timedelta(minutes=30))
+
+    assert shifted.next_change(MONDAY) == MONDAY.replace(hour=11)
+    assert oh.next_change(MONDAY) == MONDAY.replace(hour=10)
+
+
+def test_shift_must_be_whole_minutes():
+    with pytest.raises(ValueError):
+        OpeningHours("24/7").shift(timedelta(seconds=30))
+
+
+def test_shift_survives_json():
+    oh = OpeningHours("Mo 10:00-12:00").shift(timedelta(hours=2))
+    rebuilt = OpeningHours.from_ast_json(oh.to_ast_json())
+    assert rebuilt.next_change(MONDAY) == MONDAY.replace(hour=12)
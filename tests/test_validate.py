@@ -0,0 +1,24 @@
+import pytest
+
+from opening_hours import OpeningHours, validate_and_normalize
+
+
+def test_normalize_whitespaces():
+    assert validate_and_normalize("Mo-Fr  10:00-18:00") == "Mo-Fr 10:00-18:00"
+
+
+def test_normalized_expression_is_equivalent():
+    expression = 'Mo-Fr 10:00-12:00,14:00-18:00; Sa 10:00-12:00 "appointment only"'
+    normalized = validate_and_normalize(expression)
+
+    assert OpeningHours(normalized).to_ast_json() == OpeningHours(expression).to_ast_json()
+
+
+def test_invalid_expression_raises():
+    with pytest.raises(SyntaxError):
+        validate_and_normalize("24/24")
+
+
+def test_unsupported_selectors_raise():
+    with pytest.raises(ValueError):
+        validate_and_normalize("Dec 25 off")
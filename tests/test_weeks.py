@@ -0,0 +1,26 @@
+from datetime import date, datetime, time
+
+from opening_hours import OpeningHours
+
+
+def test_weeks_cover_the_window():
+    weeks = list(
+        OpeningHours("Mo 10:00-12:00").iter_weeks(datetime(2020, 11, 4), datetime(2020, 11, 10))
+    )
+
+    assert [monday for monday, _ in weeks] == [date(2020, 11, 2), date(2020, 11, 9)]
+    assert list(weeks[0][1]) == list(range(7))
+    assert weeks[0][1][0] == [(time(10, 0), time(12, 0))]
+    assert weeks[0][1][1] == []
+
+
+def test_weeks_are_computed_lazily():
+    weeks = OpeningHours("Mo 10:00-12:00").iter_weeks(datetime(2020, 11, 2), datetime.max)
+
+    assert next(weeks)[0] == date(2020, 11, 2)
+    assert next(weeks)[0] == date(2020, 11, 9)
+
+
+def test_empty_window():
+    start = datetime(2020, 11, 4)
+    assert list(OpeningHours("24/7").iter_weeks(start, start)) == []
@@ -0,0 +1,39 @@
+from datetime import datetime, timedelta
+
+import pytest
+
+from opening_hours import OpeningHours
+
+EXPRESSION = "2020-2025 Mo-Fr 10:00-18:00"
+
+
+@pytest.mark.parametrize("time", [datetime(2019, 11, 4, 12), datetime(2030, 11, 4, 12)])
+def test_state_outside_years(time):
+    oh = OpeningHours(EXPRESSION)
+
+    assert oh.state(time) == "closed"
+    assert oh.state(time, unknown_outside_years=False) == "closed"
+    assert oh.state(time, unknown_outside_years=True) == "unknown"
+
+
+def test_state_inside_years():
+    oh = OpeningHours(EXPRESSION)
+    time = datetime(2022, 11, 7, 12)
+
+    assert oh.state(time) == "open"
+    assert oh.state(time, unknown_outside_years=True) == "open"
+
+
+def test_years_of_shifted_expression():
+    # The last hours of 2025 are evaluated in 2026 once shifted one day
+    # earlier.
+    oh = OpeningHours("2020-2025 24/7").shift(timedelta(days=-1))
+
+    assert oh.state(datetime(2025, 12, 30, 12), unknown_outside_years=True) == "open"
+    assert oh.state(datetime(2025, 12, 31, 12), unknown_outside_years=True) == "unknown"
+
+
+def test_years_out_of_window():
+    oh = OpeningHours(EXPRESSION).restrict(datetime(2020, 1, 1), datetime(2021, 1, 1))
+
+    assert oh.state(datetime(2030, 11, 4, 12), unknown_outside_years=True) == "closed"